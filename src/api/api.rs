@@ -1,17 +1,19 @@
 use async_trait::async_trait;
-use serde::de::{Deserialize, DeserializeOwned};
+use serde::de::DeserializeOwned;
 
 use crate::api::models;
-use crate::error::{self, api, Result};
+use crate::error::{self, Result};
 
-use super::models::ApiResponse;
+use super::models::{ApiResponse, ErrorResponse};
 
 /// Trait to extend the json deserialization functionality of [reqwest::Response]
 ///
-/// The Infiscal API returns 200 by default, even if there were errors with the request.
-/// They instead include a JSON response with an error message and the true HTTP status code
-/// This trait calls [reqwest::Response.json] internally and converts the Infisical error response
-/// to an [infisical_rs::Error]
+/// The Infiscal API may return 200 even if there were errors with the request, in which case it
+/// includes a JSON response with an error message and the true HTTP status code. Other errors are
+/// reported with a regular HTTP error status, with or without that same JSON error body.
+/// This trait checks the HTTP status of the response, deserializes the body and converts any
+/// Infisical error response to an [infisical_rs::Error]. Responses that do not match the expected
+/// shape are reported as [error::Kind::UnexpectedResponse] errors that retain the raw body.
 #[async_trait]
 trait JsonProcessorExt {
     async fn infisical_json<T>(self) -> Result<T>
@@ -25,9 +27,20 @@ impl JsonProcessorExt for reqwest::Response {
     where
         T: DeserializeOwned,
     {
-        match self.json::<ApiResponse<T>>().await? {
-            ApiResponse::Ok(res) => Ok(res),
-            ApiResponse::Err(err) => Err(error::api(err)),
+        let status = self.status();
+        let body = self.text().await?;
+
+        if !status.is_success() {
+            return match serde_json::from_str::<ErrorResponse>(&body) {
+                Ok(err) => Err(error::api(err)),
+                Err(e) => Err(error::unexpected_response(status, body, e)),
+            };
+        }
+
+        match serde_json::from_str::<ApiResponse<T>>(&body) {
+            Ok(ApiResponse::Ok(res)) => Ok(res),
+            Ok(ApiResponse::Err(err)) => Err(error::api(err)),
+            Err(e) => Err(error::unexpected_response(status, body, e)),
         }
     }
 }
@@ -67,9 +80,6 @@ pub async fn get_organization_memberships(
         request.base_url, request.organization_id
     );
 
-    let res = client.get(&endpoint).send().await?;
-    println!("{}", res.text().await?);
-
     Ok(client
         .get(endpoint)
         .send()
@@ -88,7 +98,7 @@ pub async fn update_organization_membership(
     );
     Ok(client
         .patch(endpoint)
-        .body(request.role)
+        .json(&request)
         .send()
         .await?
         .infisical_json::<models::UpdateOrganizationMembershipResponse>()
@@ -111,6 +121,20 @@ pub async fn delete_organization_membership(
         .await?)
 }
 
+pub async fn invite_organization_member(
+    client: &reqwest::Client,
+    request: models::InviteOrganizationMemberRequest,
+) -> Result<models::InviteOrganizationMemberResponse> {
+    let endpoint = format!("{}/v1/invite-org/signup", request.base_url);
+    Ok(client
+        .post(endpoint)
+        .json(&request)
+        .send()
+        .await?
+        .infisical_json::<models::InviteOrganizationMemberResponse>()
+        .await?)
+}
+
 pub async fn get_organization_projects(
     client: &reqwest::Client,
     request: models::GetProjectsRequest,
@@ -128,6 +152,131 @@ pub async fn get_organization_projects(
         .await?)
 }
 
+pub async fn get_project(
+    client: &reqwest::Client,
+    request: models::GetProjectRequest,
+) -> Result<models::GetProjectResponse> {
+    let endpoint = format!("{}/v1/workspace/{}", request.base_url, request.workspace_id);
+    Ok(client
+        .get(endpoint)
+        .send()
+        .await?
+        .infisical_json::<models::GetProjectResponse>()
+        .await?)
+}
+
+pub async fn create_project(
+    client: &reqwest::Client,
+    request: models::CreateProjectRequest,
+) -> Result<models::CreateProjectResponse> {
+    let endpoint = format!("{}/v1/workspace", request.base_url);
+    Ok(client
+        .post(endpoint)
+        .json(&request)
+        .send()
+        .await?
+        .infisical_json::<models::CreateProjectResponse>()
+        .await?)
+}
+
+pub async fn rename_project(
+    client: &reqwest::Client,
+    request: models::RenameProjectRequest,
+) -> Result<models::RenameProjectResponse> {
+    let endpoint = format!(
+        "{}/v1/workspace/{}/name",
+        request.base_url, request.workspace_id
+    );
+    Ok(client
+        .post(endpoint)
+        .json(&request)
+        .send()
+        .await?
+        .infisical_json::<models::RenameProjectResponse>()
+        .await?)
+}
+
+pub async fn delete_project(
+    client: &reqwest::Client,
+    request: models::DeleteProjectRequest,
+) -> Result<models::DeleteProjectResponse> {
+    let endpoint = format!("{}/v1/workspace/{}", request.base_url, request.workspace_id);
+    Ok(client
+        .delete(endpoint)
+        .send()
+        .await?
+        .infisical_json::<models::DeleteProjectResponse>()
+        .await?)
+}
+
+pub async fn create_environment(
+    client: &reqwest::Client,
+    request: models::CreateEnvironmentRequest,
+) -> Result<models::EnvironmentResponse> {
+    let endpoint = format!(
+        "{}/v2/workspace/{}/environments",
+        request.base_url, request.workspace_id
+    );
+    Ok(client
+        .post(endpoint)
+        .json(&request)
+        .send()
+        .await?
+        .infisical_json::<models::EnvironmentResponse>()
+        .await?)
+}
+
+pub async fn update_environment(
+    client: &reqwest::Client,
+    request: models::UpdateEnvironmentRequest,
+) -> Result<models::EnvironmentResponse> {
+    let endpoint = format!(
+        "{}/v2/workspace/{}/environments",
+        request.base_url, request.workspace_id
+    );
+    Ok(client
+        .put(endpoint)
+        .json(&request)
+        .send()
+        .await?
+        .infisical_json::<models::EnvironmentResponse>()
+        .await?)
+}
+
+pub async fn reorder_environments(
+    client: &reqwest::Client,
+    request: models::ReorderEnvironmentsRequest,
+) -> Result<models::ReorderEnvironmentsResponse> {
+    let endpoint = format!(
+        "{}/v2/workspace/{}/environments",
+        request.base_url, request.workspace_id
+    );
+    Ok(client
+        .patch(endpoint)
+        .json(&request)
+        .send()
+        .await?
+        .infisical_json::<models::ReorderEnvironmentsResponse>()
+        .await?)
+}
+
+pub async fn delete_environment(
+    client: &reqwest::Client,
+    request: models::DeleteEnvironmentRequest,
+) -> Result<models::EnvironmentResponse> {
+    let endpoint = format!(
+        "{}/v2/workspace/{}/environments",
+        request.base_url, request.workspace_id
+    );
+    Ok(client
+        .delete(endpoint)
+        .json(&request)
+        .send()
+        .await?
+        .infisical_json::<models::EnvironmentResponse>()
+        .await?)
+}
+
 pub async fn get_project_memberships(
     client: &reqwest::Client,
     request: models::GetProjectMembershipsRequest,
@@ -154,7 +303,7 @@ pub async fn update_project_membership(
     );
     Ok(client
         .patch(endpoint)
-        .body(request.role)
+        .json(&request)
         .send()
         .await?
         .infisical_json::<models::UpdateProjectMembershipResponse>()
@@ -177,6 +326,96 @@ pub async fn delete_project_membership(
         .await?)
 }
 
+pub async fn set_denied_permissions(
+    client: &reqwest::Client,
+    request: models::SetDeniedPermissionsRequest,
+) -> Result<models::SetDeniedPermissionsResponse> {
+    let endpoint = format!(
+        "{}/v1/membership/{}/deny-permissions",
+        request.base_url, request.membership_id
+    );
+    Ok(client
+        .post(endpoint)
+        .json(&request)
+        .send()
+        .await?
+        .infisical_json::<models::SetDeniedPermissionsResponse>()
+        .await?)
+}
+
+pub async fn get_roles(
+    client: &reqwest::Client,
+    request: models::GetRolesRequest,
+) -> Result<models::GetRolesResponse> {
+    let endpoint = format!("{}/v1/roles", request.base_url);
+    Ok(client
+        .get(endpoint)
+        .query(&request)
+        .send()
+        .await?
+        .infisical_json::<models::GetRolesResponse>()
+        .await?)
+}
+
+pub async fn create_role(
+    client: &reqwest::Client,
+    request: models::CreateRoleRequest,
+) -> Result<models::RoleResponse> {
+    let endpoint = format!("{}/v1/roles", request.base_url);
+    Ok(client
+        .post(endpoint)
+        .json(&request)
+        .send()
+        .await?
+        .infisical_json::<models::RoleResponse>()
+        .await?)
+}
+
+pub async fn update_role(
+    client: &reqwest::Client,
+    request: models::UpdateRoleRequest,
+) -> Result<models::RoleResponse> {
+    let endpoint = format!("{}/v1/roles/{}", request.base_url, request.role_id);
+    Ok(client
+        .patch(endpoint)
+        .json(&request)
+        .send()
+        .await?
+        .infisical_json::<models::RoleResponse>()
+        .await?)
+}
+
+pub async fn invite_project_member(
+    client: &reqwest::Client,
+    request: models::InviteProjectMemberRequest,
+) -> Result<models::InviteProjectMemberResponse> {
+    let endpoint = format!(
+        "{}/v1/workspace/{}/invite-signup",
+        request.base_url, request.workspace_id
+    );
+    Ok(client
+        .post(endpoint)
+        .json(&request)
+        .send()
+        .await?
+        .infisical_json::<models::InviteProjectMemberResponse>()
+        .await?)
+}
+
+pub async fn upload_project_key(
+    client: &reqwest::Client,
+    request: models::UploadProjectKeyRequest,
+) -> Result<models::UploadProjectKeyResponse> {
+    let endpoint = format!("{}/v1/key/{}", request.base_url, request.workspace_id);
+    Ok(client
+        .post(endpoint)
+        .json(&request)
+        .send()
+        .await?
+        .infisical_json::<models::UploadProjectKeyResponse>()
+        .await?)
+}
+
 pub async fn get_project_key(
     client: &reqwest::Client,
     request: models::GetProjectKeyRequest,
@@ -203,6 +442,7 @@ pub async fn get_project_logs(
     );
     Ok(client
         .get(endpoint)
+        .query(&request)
         .send()
         .await?
         .infisical_json::<models::GetProjectLogsResponse>()
@@ -227,6 +467,24 @@ pub async fn get_project_snapshots(
         .await?)
 }
 
+pub async fn get_project_secret_versions(
+    client: &reqwest::Client,
+    request: models::GetProjectSecretVersionsRequest,
+) -> Result<models::GetProjectSecretVersionsResponse> {
+    let endpoint = format!(
+        "{}/v1/secret/{}/secret-versions",
+        request.base_url, request.secret_id
+    );
+
+    Ok(client
+        .get(endpoint)
+        .query(&request)
+        .send()
+        .await?
+        .infisical_json::<models::GetProjectSecretVersionsResponse>()
+        .await?)
+}
+
 pub async fn roll_back_to_snapshot(
     client: &reqwest::Client,
     request: models::RollbackProjectToSnapshotRequest,
@@ -237,7 +495,7 @@ pub async fn roll_back_to_snapshot(
     );
     Ok(client
         .post(endpoint)
-        .body(request.version.to_string())
+        .json(&request)
         .send()
         .await?
         .infisical_json::<models::RollbackProjectToSnapshotResponse>()
@@ -259,6 +517,36 @@ pub async fn create_project_secrets(
         .await?)
 }
 
+pub async fn update_project_secrets(
+    client: &reqwest::Client,
+    request: models::UpdateSecretsRequest,
+) -> Result<models::UpdateSecretsResponse> {
+    let endpoint = format!("{}/v2/secrets", request.base_url);
+
+    Ok(client
+        .patch(endpoint)
+        .json(&request)
+        .send()
+        .await?
+        .infisical_json::<models::UpdateSecretsResponse>()
+        .await?)
+}
+
+pub async fn delete_project_secrets(
+    client: &reqwest::Client,
+    request: models::DeleteProjectSecretsRequest,
+) -> Result<models::DeleteProjectSecretsResponse> {
+    let endpoint = format!("{}/v2/secrets", request.base_url);
+
+    Ok(client
+        .delete(endpoint)
+        .json(&request)
+        .send()
+        .await?
+        .infisical_json::<models::DeleteProjectSecretsResponse>()
+        .await?)
+}
+
 /// Gets all of the secrets belonging the workspace provided in the request
 pub async fn get_project_secrets(
     client: &reqwest::Client,
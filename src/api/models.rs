@@ -1,22 +1,90 @@
 use std::collections::HashMap;
+use std::fmt;
 
 use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
-use time::{serde::iso8601, OffsetDateTime};
+use time::serde::{iso8601, rfc3339};
+use time::OffsetDateTime;
 
-use crate::error::Result;
-use crate::utils::aes256gcm::{decrypt, Encryption};
+use crate::error::{self, Result};
+use crate::keys::{ProjectKey, UserPrivateKey};
+use crate::secret::{SecretBytes, SecretString};
+use crate::utils::aes256gcm::{self, decrypt, AesKey, Encryption};
+use crate::utils::{argon2, base64, hex};
+
+/// Defines an enum of the values of a string field of the API
+///
+/// The enum is (de)serialized as a plain string. Each variant names the value it stands for,
+/// except the last, `Other(String)`, which keeps values this crate doesn't know about.
+macro_rules! string_enum {
+    (
+        $(#[$meta:meta])*
+        pub enum $name:ident {
+            $($(#[$variant_meta:meta])* $variant:ident $(= $value:literal)? $(($other:ty))?,)*
+        }
+    ) => {
+        $(#[$meta])*
+        #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, Hash)]
+        #[serde(from = "String", into = "String")]
+        pub enum $name {
+            $($(#[$variant_meta])* $variant $(($other))?,)*
+        }
+
+        impl $name {
+            /// Returns the value as Infisical names it
+            pub fn as_str(&self) -> &str {
+                match self {
+                    $($($name::$variant => $value,)?)*
+                    $name::Other(value) => value,
+                }
+            }
+        }
+
+        impl From<&str> for $name {
+            fn from(value: &str) -> $name {
+                match value {
+                    $($($value => $name::$variant,)?)*
+                    other => $name::Other(other.to_string()),
+                }
+            }
+        }
+
+        impl From<String> for $name {
+            fn from(value: String) -> $name {
+                $name::from(value.as_str())
+            }
+        }
+
+        impl From<$name> for String {
+            fn from(value: $name) -> String {
+                match value {
+                    $name::Other(value) => value,
+                    value => value.as_str().to_string(),
+                }
+            }
+        }
+
+        impl fmt::Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str(self.as_str())
+            }
+        }
+    };
+}
 
 /// An enum that represents the possible return values from the Infisical API
 ///
-/// Infisical returns a 200 response even for errors on their side, but do provide a JSON response
+/// Infisical may return a 200 response even for errors on their side, but does provide a JSON response
 /// with traditional HTTP response codes and additional error information.
+///
+/// The error is tried first, since some success responses only have a message, which an error
+/// response has as well.
 #[derive(Deserialize)]
 #[serde(untagged)]
 pub enum ApiResponse<T> {
-    Ok(T),
     Err(ErrorResponse),
+    Ok(T),
 }
 
 /// Represents the expected request body for the `/v2/users/me` endpoint
@@ -46,6 +114,12 @@ pub struct User {
     pub salt: String,
     pub iv: String,
     pub tag: String,
+    /// The key that encrypts the private key of encryption version 2 users, itself encrypted with
+    /// a key derived from the user's password
+    pub protected_key: Option<String>,
+    #[serde(rename = "protectedKeyIV")]
+    pub protected_key_iv: Option<String>,
+    pub protected_key_tag: Option<String>,
     #[serde(alias = "__v")]
     pub v: u8,
     pub devices: Vec<UserDevice>,
@@ -56,6 +130,67 @@ pub struct User {
     pub audit: Audit,
 }
 
+impl User {
+    /// Decrypts the user's private key with their password
+    ///
+    /// Users with encryption version 1 have their private key encrypted with their password.
+    /// Users with encryption version 2 have it encrypted with a protected key, which is itself
+    /// encrypted with a key derived from their password and salt using Argon2id.
+    pub fn decrypt_private_key(&self, password: &str) -> Result<UserPrivateKey> {
+        let private_key = match self.encryption_version {
+            None | Some(1) => decrypt(&self.encrypted_private_key, &self.iv, &self.tag, password)?,
+            Some(2) => {
+                let private_key = aes256gcm::decrypt_bytes(
+                    &self.encrypted_private_key,
+                    &self.iv,
+                    &self.tag,
+                    &self.decrypt_protected_key(password)?,
+                )?;
+
+                String::from_utf8(private_key).map_err(error::utf8)?
+            }
+            Some(version) => {
+                return Err(error::decode(
+                    "encryptionVersion",
+                    format!("unsupported encryption version {}", version),
+                ))
+            }
+        };
+
+        UserPrivateKey::from_base64(SecretString::from(private_key).expose())
+    }
+
+    fn decrypt_protected_key(&self, password: &str) -> Result<AesKey> {
+        let missing = |field| error::decode(field, "required for encryption version 2");
+        let protected_key = self
+            .protected_key
+            .as_ref()
+            .ok_or_else(|| missing("protectedKey"))?;
+        let protected_key_iv = self
+            .protected_key_iv
+            .as_ref()
+            .ok_or_else(|| missing("protectedKeyIV"))?;
+        let protected_key_tag = self
+            .protected_key_tag
+            .as_ref()
+            .ok_or_else(|| missing("protectedKeyTag"))?;
+
+        let derived_key = AesKey::from_bytes(argon2::derive_key(password, &self.salt)?);
+        let protected_key = aes256gcm::decrypt_bytes(
+            protected_key,
+            protected_key_iv,
+            protected_key_tag,
+            &derived_key,
+        )?;
+
+        // The protected key is stored as a hexadecimal string
+        let protected_key =
+            SecretString::from(String::from_utf8(protected_key).map_err(error::utf8)?);
+
+        hex::decode_array::<32>("protectedKey", protected_key.expose()).map(AesKey::from_bytes)
+    }
+}
+
 #[derive(Deserialize, Clone, Debug)]
 #[serde(rename_all = "camelCase")]
 pub struct SimpleUser {
@@ -64,6 +199,8 @@ pub struct SimpleUser {
     pub email: String,
     pub first_name: String,
     pub last_name: String,
+    /// The public key of the user, if the endpoint includes it and the user has completed signup
+    pub public_key: Option<String>,
     #[serde(alias = "__v")]
     pub v: u8,
     pub devices: Vec<UserDevice>,
@@ -115,24 +252,108 @@ pub struct GetOrganizationMembershipsResponse {
     pub memberships: Vec<OrganizationMembership>,
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, Clone, Debug)]
 pub struct OrganizationMembership {
     #[serde(alias = "_id")]
     pub id: String,
     pub organization: String,
-    pub role: String,
-    pub status: String,
-    pub user: SimpleUser,
+    pub role: Role,
+    pub status: MembershipStatus,
+    /// The user, which is missing for invitations of users who haven't signed up yet
+    pub user: Option<SimpleUser>,
+    /// The email the invitation was sent to, if the membership started as an invitation
+    #[serde(alias = "inviteEmail")]
+    pub invite_email: Option<String>,
     #[serde(flatten)]
     pub audit: Audit,
 }
 
+impl OrganizationMembership {
+    /// Returns the email of the user, or the email the invitation was sent to
+    pub fn email(&self) -> Option<&str> {
+        self.user
+            .as_ref()
+            .map(|user| user.email.as_str())
+            .or(self.invite_email.as_deref())
+    }
+
+    /// Returns true if the invitation to the organization hasn't been accepted yet
+    pub fn is_pending(&self) -> bool {
+        matches!(
+            self.status,
+            MembershipStatus::Invited | MembershipStatus::Verified
+        )
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InviteOrganizationMemberRequest {
+    #[serde(skip)]
+    pub base_url: String,
+    pub invitee_email: String,
+    pub organization_id: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InviteOrganizationMemberResponse {
+    pub message: String,
+    /// The link to complete the signup, only returned when Infisical can't send emails
+    pub complete_invite_link: Option<String>,
+}
+
+string_enum! {
+    /// The role of a member of an organization or project
+    ///
+    /// Roles are sent and received as plain strings, and roles this crate doesn't know about are
+    /// kept as [`Role::Other`].
+    pub enum Role {
+        /// Owns the organization
+        Owner = "owner",
+        /// Manages members and settings
+        Admin = "admin",
+        /// Reads and writes secrets
+        Member = "member",
+        /// Only reads secrets
+        Viewer = "viewer",
+        /// Has no access until given permissions
+        NoAccess = "no-access",
+        /// Has the permissions of a custom role
+        Custom = "custom",
+        /// A role this crate doesn't know about
+        Other(String),
+    }
+}
+
+string_enum! {
+    /// The status of a membership in an organization
+    ///
+    /// Statuses this crate doesn't know about are kept as [`MembershipStatus::Other`].
+    pub enum MembershipStatus {
+        /// The user has been invited but hasn't signed up yet
+        Invited = "invited",
+        /// The user has verified their email but hasn't finished signing up
+        Verified = "verified",
+        /// The user has accepted the invitation
+        Accepted = "accepted",
+        /// The user has finished signing up
+        Completed = "completed",
+        /// A status this crate doesn't know about
+        Other(String),
+    }
+}
+
+#[derive(Serialize)]
 pub struct UpdateOrganizationMembershipRequest {
     /// The base url for the Infisical API
+    #[serde(skip)]
     pub base_url: String,
+    #[serde(skip)]
     pub organization_id: String,
+    #[serde(skip)]
     pub membership_id: String,
-    pub role: String,
+    pub role: Role,
 }
 
 #[derive(Deserialize)]
@@ -162,7 +383,7 @@ pub struct GetProjectsResponse {
     pub workspaces: Vec<Workspace>,
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, Clone, Debug)]
 pub struct Workspace {
     #[serde(alias = "_id")]
     pub id: String,
@@ -171,12 +392,148 @@ pub struct Workspace {
     pub environments: Vec<Environment>,
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, Clone, Debug, PartialEq, Eq)]
 pub struct Environment {
     pub name: String,
     pub slug: String,
 }
 
+impl Environment {
+    /// Checks that a slug is one Infisical accepts for an environment
+    ///
+    /// Slugs are between 1 and 64 characters of lowercase ASCII letters, digits, `-` and `_`, and
+    /// must start and end with a letter or digit.
+    pub fn validate_slug(slug: &str) -> Result<()> {
+        let invalid = |reason: &str| {
+            error::invalid_input("environment slug", format!("{:?} {}", slug, reason))
+        };
+
+        if slug.is_empty() || slug.len() > 64 {
+            return Err(invalid("must be between 1 and 64 characters"));
+        }
+
+        if !slug
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-' || c == '_')
+        {
+            return Err(invalid(
+                "may only contain lowercase letters, digits, '-' and '_'",
+            ));
+        }
+
+        if slug.starts_with(['-', '_']) || slug.ends_with(['-', '_']) {
+            return Err(invalid("must start and end with a letter or digit"));
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateEnvironmentRequest {
+    #[serde(skip)]
+    pub base_url: String,
+    #[serde(skip)]
+    pub workspace_id: String,
+    pub environment_name: String,
+    pub environment_slug: String,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateEnvironmentRequest {
+    #[serde(skip)]
+    pub base_url: String,
+    #[serde(skip)]
+    pub workspace_id: String,
+    pub old_environment_slug: String,
+    pub environment_name: String,
+    pub environment_slug: String,
+}
+
+/// Swaps the positions of two environments
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReorderEnvironmentsRequest {
+    #[serde(skip)]
+    pub base_url: String,
+    #[serde(skip)]
+    pub workspace_id: String,
+    pub environment_slug: String,
+    pub environment_name: String,
+    pub other_environment_slug: String,
+    pub other_environment_name: String,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeleteEnvironmentRequest {
+    #[serde(skip)]
+    pub base_url: String,
+    #[serde(skip)]
+    pub workspace_id: String,
+    pub environment_slug: String,
+}
+
+#[derive(Deserialize)]
+pub struct EnvironmentResponse {
+    pub environment: Environment,
+}
+
+#[derive(Deserialize)]
+pub struct ReorderEnvironmentsResponse {
+    pub message: String,
+}
+
+pub struct GetProjectRequest {
+    pub base_url: String,
+    pub workspace_id: String,
+}
+
+#[derive(Deserialize)]
+pub struct GetProjectResponse {
+    pub workspace: Workspace,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateProjectRequest {
+    #[serde(skip)]
+    pub base_url: String,
+    pub workspace_name: String,
+    pub organization_id: String,
+}
+
+#[derive(Deserialize)]
+pub struct CreateProjectResponse {
+    pub workspace: Workspace,
+}
+
+#[derive(Serialize)]
+pub struct RenameProjectRequest {
+    #[serde(skip)]
+    pub base_url: String,
+    #[serde(skip)]
+    pub workspace_id: String,
+    pub name: String,
+}
+
+#[derive(Deserialize)]
+pub struct RenameProjectResponse {
+    pub workspace: Workspace,
+}
+
+pub struct DeleteProjectRequest {
+    pub base_url: String,
+    pub workspace_id: String,
+}
+
+#[derive(Deserialize)]
+pub struct DeleteProjectResponse {
+    pub workspace: Workspace,
+}
+
 pub struct GetProjectMembershipsRequest {
     pub base_url: String,
     pub workspace_id: String,
@@ -191,20 +548,224 @@ pub struct GetProjectMembershipsResponse {
 pub struct ProjectMembership {
     #[serde(alias = "_id")]
     pub id: String,
-    pub role: String,
+    pub role: Role,
     pub user: SimpleUser,
     pub workspace: String,
     #[serde(flatten)]
     pub audit: Audit,
     #[serde(alias = "deniedPermissions")]
-    pub denied_permissions: Vec<String>,
+    pub denied_permissions: Vec<DeniedPermission>,
+    /// The id of the custom role of the member, if their role is [`Role::Custom`]
+    #[serde(alias = "customRole", default)]
+    pub custom_role: Option<String>,
+}
+
+impl ProjectMembership {
+    /// Returns true if `ability` is denied to the member in the environment `environment_slug`
+    pub fn is_denied(&self, ability: &Ability, environment_slug: &str) -> bool {
+        self.denied_permissions
+            .iter()
+            .any(|p| &p.ability == ability && p.environment_slug == environment_slug)
+    }
 }
 
+string_enum! {
+    /// An ability that can be denied to a project member per environment
+    ///
+    /// Abilities this crate doesn't know about are kept as [`Ability::Other`].
+    pub enum Ability {
+        /// Reading the secrets of the environment
+        Read = "read",
+        /// Creating, updating and deleting the secrets of the environment
+        Write = "write",
+        /// An ability this crate doesn't know about
+        Other(String),
+    }
+}
+
+/// An ability denied to a project member in one environment
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, Hash)]
+#[serde(rename_all = "camelCase")]
+pub struct DeniedPermission {
+    /// The denied ability
+    pub ability: Ability,
+    /// The slug of the environment the ability is denied in
+    pub environment_slug: String,
+}
+
+impl DeniedPermission {
+    /// Denies reading the secrets of an environment
+    pub fn read(environment_slug: &str) -> DeniedPermission {
+        DeniedPermission {
+            ability: Ability::Read,
+            environment_slug: environment_slug.to_string(),
+        }
+    }
+
+    /// Denies changing the secrets of an environment
+    pub fn write(environment_slug: &str) -> DeniedPermission {
+        DeniedPermission {
+            ability: Ability::Write,
+            environment_slug: environment_slug.to_string(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct SetDeniedPermissionsRequest {
+    #[serde(skip)]
+    pub base_url: String,
+    #[serde(skip)]
+    pub membership_id: String,
+    pub permissions: Vec<DeniedPermission>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SetDeniedPermissionsResponse {
+    pub permissions_denied: Vec<DeniedPermission>,
+}
+
+string_enum! {
+    /// An action a role may be allowed to perform on a subject
+    ///
+    /// Actions this crate doesn't know about are kept as [`PermissionAction::Other`].
+    pub enum PermissionAction {
+        /// Reading the subject
+        Read = "read",
+        /// Creating the subject
+        Create = "create",
+        /// Changing the subject
+        Edit = "edit",
+        /// Deleting the subject
+        Delete = "delete",
+        /// An action this crate doesn't know about
+        Other(String),
+    }
+}
+
+/// A rule of a role, allowing or forbidding an action on a subject
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct RolePermission {
+    /// The action the rule is about
+    pub action: PermissionAction,
+    /// What the action is performed on, e.g. `secrets`, `member` or `settings`
+    pub subject: String,
+    /// Conditions that restrict the rule, e.g. `{"environment": "dev"}`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub conditions: Option<Value>,
+    /// True if the rule forbids the action instead of allowing it
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub inverted: bool,
+}
+
+impl RolePermission {
+    /// Allows `action` on `subject`
+    pub fn allow(action: PermissionAction, subject: &str) -> RolePermission {
+        RolePermission {
+            action,
+            subject: subject.to_string(),
+            conditions: None,
+            inverted: false,
+        }
+    }
+
+    /// Restricts the rule to a single environment
+    pub fn in_environment(mut self, environment_slug: &str) -> RolePermission {
+        self.conditions = Some(serde_json::json!({ "environment": environment_slug }));
+        self
+    }
+}
+
+/// A role of an organization or project along with its permissions
+///
+/// The built-in roles are listed along with the custom ones, but have no id.
+#[derive(Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct CustomRole {
+    #[serde(alias = "_id", default)]
+    pub id: Option<String>,
+    pub name: String,
+    pub slug: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    pub permissions: Vec<RolePermission>,
+    /// The project of the role, or `None` for organization roles
+    #[serde(default)]
+    pub workspace: Option<String>,
+}
+
+/// The name, slug, description and permissions of a custom role
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct RoleDefinition {
+    pub name: String,
+    pub slug: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    pub permissions: Vec<RolePermission>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GetRolesRequest {
+    #[serde(skip)]
+    pub base_url: String,
+    pub org_id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub workspace_id: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct GetRolesResponse {
+    pub data: RolesData,
+}
+
+#[derive(Deserialize)]
+pub struct RolesData {
+    pub roles: Vec<CustomRole>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateRoleRequest {
+    #[serde(skip)]
+    pub base_url: String,
+    pub org_id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub workspace_id: Option<String>,
+    #[serde(flatten)]
+    pub role: RoleDefinition,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateRoleRequest {
+    #[serde(skip)]
+    pub base_url: String,
+    #[serde(skip)]
+    pub role_id: String,
+    pub org_id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub workspace_id: Option<String>,
+    #[serde(flatten)]
+    pub role: RoleDefinition,
+}
+
+#[derive(Deserialize)]
+pub struct RoleResponse {
+    pub role: CustomRole,
+}
+
+#[derive(Serialize)]
 pub struct UpdateProjectMembershipRequest {
+    #[serde(skip)]
     pub base_url: String,
+    #[serde(skip)]
     pub workspace_id: String,
+    #[serde(skip)]
     pub membership_id: String,
-    pub role: String,
+    pub role: Role,
 }
 
 #[derive(Deserialize)]
@@ -223,6 +784,54 @@ pub struct DeleteProjectMembershipResponse {
     pub membership: ProjectMembership,
 }
 
+#[derive(Serialize)]
+pub struct InviteProjectMemberRequest {
+    #[serde(skip)]
+    pub base_url: String,
+    #[serde(skip)]
+    pub workspace_id: String,
+    pub email: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InviteProjectMemberResponse {
+    pub invitee: ProjectInvitee,
+}
+
+/// The user that was added to a project
+#[derive(Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ProjectInvitee {
+    #[serde(alias = "_id")]
+    pub id: String,
+    pub email: String,
+    /// The public key of the user, which is missing if they have not completed their signup
+    pub public_key: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct UploadProjectKeyRequest {
+    #[serde(skip)]
+    pub base_url: String,
+    #[serde(skip)]
+    pub workspace_id: String,
+    pub key: ProjectKeyUpload,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProjectKeyUpload {
+    pub user_id: String,
+    pub encrypted_key: String,
+    pub nonce: String,
+}
+
+#[derive(Deserialize)]
+pub struct UploadProjectKeyResponse {
+    pub message: String,
+}
+
 pub struct GetProjectKeyRequest {
     pub base_url: String,
     pub workspace_id: String,
@@ -244,14 +853,198 @@ pub struct Sender {
     pub public_key: String,
 }
 
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
 pub struct GetProjectLogsRequest {
+    #[serde(skip)]
     pub base_url: String,
+    #[serde(skip)]
     pub workspace_id: String,
-    pub user_id: String,
-    pub offset: String,
-    pub limit: String,
-    pub sort_by: String,
-    pub action_names: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub user_id: Option<String>,
+    pub offset: u32,
+    pub limit: u32,
+    pub sort_by: SortOrder,
+    /// The action names joined with commas
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub action_names: Option<String>,
+    #[serde(with = "rfc3339::option", skip_serializing_if = "Option::is_none")]
+    pub start_date: Option<OffsetDateTime>,
+    #[serde(with = "rfc3339::option", skip_serializing_if = "Option::is_none")]
+    pub end_date: Option<OffsetDateTime>,
+}
+
+/// The order logs are returned in
+#[derive(Serialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum SortOrder {
+    /// The most recent logs first
+    #[default]
+    Recent,
+    /// The oldest logs first
+    Oldest,
+}
+
+string_enum! {
+    /// An action recorded in the logs of a project
+    ///
+    /// Actions this crate doesn't know about are kept as [`LogAction::Other`].
+    pub enum LogAction {
+        /// Secrets were added
+        AddSecrets = "addSecrets",
+        /// Secrets were updated
+        UpdateSecrets = "updateSecrets",
+        /// Secrets were read
+        ReadSecrets = "readSecrets",
+        /// Secrets were deleted
+        DeleteSecrets = "deleteSecrets",
+        /// An action this crate doesn't know about
+        Other(String),
+    }
+}
+
+/// Filters, orders and pages the logs returned by [`Client::get_project_logs`]
+///
+/// ```
+/// use infisical_api::api::models::{LogAction, LogQuery, SortOrder};
+///
+/// let query = LogQuery::new()
+///     .user("6420a3b4c5d6e7f8a9b0c1d2")
+///     .action(LogAction::UpdateSecrets)
+///     .action(LogAction::DeleteSecrets)
+///     .sort(SortOrder::Oldest)
+///     .limit(100);
+/// ```
+///
+/// [`Client::get_project_logs`]: crate::Client::get_project_logs
+#[derive(Clone, Debug, PartialEq)]
+pub struct LogQuery {
+    user_id: Option<String>,
+    actions: Vec<LogAction>,
+    since: Option<OffsetDateTime>,
+    until: Option<OffsetDateTime>,
+    sort: SortOrder,
+    offset: u32,
+    limit: u32,
+}
+
+impl LogQuery {
+    /// Constructs a query for the 50 most recent logs
+    pub fn new() -> LogQuery {
+        LogQuery {
+            user_id: None,
+            actions: Vec::new(),
+            since: None,
+            until: None,
+            sort: SortOrder::Recent,
+            offset: 0,
+            limit: 50,
+        }
+    }
+
+    /// Only returns the logs of the user with the id `user_id`
+    pub fn user(mut self, user_id: &str) -> LogQuery {
+        self.user_id = Some(user_id.to_string());
+        self
+    }
+
+    /// Only returns logs with `action`, in addition to any other actions already added
+    pub fn action(mut self, action: LogAction) -> LogQuery {
+        self.actions.push(action);
+        self
+    }
+
+    /// Only returns logs created at or after `since`
+    pub fn since(mut self, since: OffsetDateTime) -> LogQuery {
+        self.since = Some(since);
+        self
+    }
+
+    /// Only returns logs created at or before `until`
+    pub fn until(mut self, until: OffsetDateTime) -> LogQuery {
+        self.until = Some(until);
+        self
+    }
+
+    /// Sets the order the logs are returned in, most recent first by default
+    pub fn sort(mut self, sort: SortOrder) -> LogQuery {
+        self.sort = sort;
+        self
+    }
+
+    /// Skips the first `offset` logs
+    pub fn offset(mut self, offset: u32) -> LogQuery {
+        self.offset = offset;
+        self
+    }
+
+    /// Returns at most `limit` logs
+    pub fn limit(mut self, limit: u32) -> LogQuery {
+        self.limit = limit;
+        self
+    }
+
+    /// Returns true if `log` is within the date range of the query and matches its user and
+    /// actions
+    ///
+    /// The filters are sent to Infisical along with the query, and the logs it returns are also
+    /// checked with this, so that only matching logs are returned even if Infisical ignores some
+    /// of the filters.
+    pub fn matches(&self, log: &ProjectLog) -> bool {
+        let created_at = log.audit.created_at;
+
+        self.since.is_none_or(|since| created_at >= since)
+            && self.until.is_none_or(|until| created_at <= until)
+            && self
+                .user_id
+                .as_ref()
+                .is_none_or(|user_id| &log.user.id == user_id)
+            && (self.actions.is_empty()
+                || log
+                    .action_names
+                    .iter()
+                    .any(|action| self.actions.contains(action)))
+    }
+
+    pub(crate) fn to_request(
+        &self,
+        base_url: &str,
+        workspace_id: &str,
+    ) -> Result<GetProjectLogsRequest> {
+        if let (Some(since), Some(until)) = (self.since, self.until) {
+            if since > until {
+                return Err(error::invalid_input(
+                    "log query",
+                    "the date range ends before it starts",
+                ));
+            }
+        }
+
+        let action_names = self
+            .actions
+            .iter()
+            .map(LogAction::as_str)
+            .collect::<Vec<_>>()
+            .join(",");
+
+        Ok(GetProjectLogsRequest {
+            base_url: base_url.to_string(),
+            workspace_id: workspace_id.to_string(),
+            user_id: self.user_id.clone(),
+            offset: self.offset,
+            limit: self.limit,
+            sort_by: self.sort,
+            action_names: (!action_names.is_empty()).then_some(action_names),
+            start_date: self.since,
+            end_date: self.until,
+        })
+    }
+}
+
+impl Default for LogQuery {
+    fn default() -> LogQuery {
+        LogQuery::new()
+    }
 }
 
 #[derive(Deserialize)]
@@ -267,7 +1060,7 @@ pub struct ProjectLog {
     pub user: SimpleUser,
     pub workspace: String,
     #[serde(alias = "actionNames")]
-    pub action_names: Vec<String>,
+    pub action_names: Vec<LogAction>,
     pub actions: Vec<ProjectLogAction>,
     pub channel: String,
     #[serde(alias = "ipAddress")]
@@ -278,7 +1071,7 @@ pub struct ProjectLog {
 
 #[derive(Deserialize)]
 pub struct ProjectLogAction {
-    pub name: String,
+    pub name: LogAction,
     pub user: String,
     pub workspace: String,
     pub payload: Vec<ProjectLogActionPayload>,
@@ -286,10 +1079,15 @@ pub struct ProjectLogAction {
 
 #[derive(Deserialize)]
 pub struct ProjectLogActionPayload {
-    #[serde(alias = "oldSecretVersion")]
-    pub old_secret_version: String,
-    #[serde(alias = "newSecretVersion")]
-    pub new_secret_version: String,
+    /// The id of the secret the action was performed on
+    #[serde(default)]
+    pub secret: Option<String>,
+    /// The id of the secret version before the action, missing for added secrets
+    #[serde(alias = "oldSecretVersion", default)]
+    pub old_secret_version: Option<String>,
+    /// The id of the secret version after the action, missing for deleted secrets
+    #[serde(alias = "newSecretVersion", default)]
+    pub new_secret_version: Option<String>,
 }
 
 #[derive(Serialize)]
@@ -298,8 +1096,8 @@ pub struct GetProjectSnapshotsRequest {
     pub base_url: String,
     #[serde(skip)]
     pub workspace_id: String,
-    pub offset: String,
-    pub limit: String,
+    pub offset: u32,
+    pub limit: u32,
 }
 
 #[derive(Deserialize)]
@@ -313,7 +1111,7 @@ pub struct SecretSnapshot {
     #[serde(alias = "_id")]
     pub id: String,
     pub workspace: String,
-    pub version: u8,
+    pub version: u32,
     #[serde(alias = "secretVersions")]
     pub secret_versions: Vec<String>,
 }
@@ -324,10 +1122,13 @@ pub struct ProjectSecretVersion {
     pub id: String,
 }
 
+#[derive(Serialize)]
 pub struct RollbackProjectToSnapshotRequest {
+    #[serde(skip)]
     pub base_url: String,
+    #[serde(skip)]
     pub workspace_id: String,
-    pub version: u8,
+    pub version: u32,
 }
 
 #[derive(Deserialize)]
@@ -339,7 +1140,7 @@ pub struct RollbackProjectToSnapshotResponse {
 pub struct RollbackSecret {
     #[serde(alias = "_id")]
     pub id: String,
-    pub version: u8,
+    pub version: u32,
     pub workspace: String,
     #[serde(alias = "type")]
     pub secret_type: Option<String>,
@@ -353,6 +1154,7 @@ pub struct RollbackSecret {
 
 #[derive(Serialize)]
 pub struct CreateProjectSecretsRequest {
+    #[serde(skip)]
     pub base_url: String,
     #[serde(rename = "workspaceId")]
     pub workspace_id: String,
@@ -377,7 +1179,9 @@ pub struct CreateProjectSecretsResponse {
     pub secrets: Vec<EncryptedSecret>,
 }
 
+#[derive(Serialize)]
 pub struct UpdateSecretsRequest {
+    #[serde(skip)]
     pub base_url: String,
     pub secrets: Vec<SecretToUpdate>,
 }
@@ -389,6 +1193,8 @@ pub struct SecretToUpdate {
     pub key: EncryptedKey,
     #[serde(flatten)]
     pub value: EncryptedValue,
+    #[serde(flatten, skip_serializing_if = "Option::is_none")]
+    pub comment: Option<EncryptedComment>,
 }
 
 #[derive(Deserialize)]
@@ -411,7 +1217,10 @@ pub struct GetProjectSecretsResponse {
     pub secrets: Vec<EncryptedSecret>,
 }
 
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
 pub struct DeleteProjectSecretsRequest {
+    #[serde(skip)]
     pub base_url: String,
     pub secret_ids: Vec<String>,
 }
@@ -421,11 +1230,14 @@ pub struct DeleteProjectSecretsResponse {
     pub secrets: Vec<EncryptedSecret>,
 }
 
+#[derive(Serialize)]
 pub struct GetProjectSecretVersionsRequest {
+    #[serde(skip)]
     pub base_url: String,
+    #[serde(skip)]
     pub secret_id: String,
-    pub offset: String,
-    pub limit: String,
+    pub offset: u32,
+    pub limit: u32,
 }
 
 #[derive(Deserialize)]
@@ -440,7 +1252,7 @@ pub struct SecretVersion {
     #[serde(alias = "_id")]
     pub id: String,
     pub secret: String,
-    pub version: u8,
+    pub version: u32,
     pub workspace: String,
     #[serde(alias = "type")]
     pub secret_type: String,
@@ -460,7 +1272,7 @@ pub struct SecretVersion {
 pub struct RollbackProjectSecretToVersionRequest {
     pub base_url: String,
     pub secret_id: String,
-    pub version: u8,
+    pub version: u32,
 }
 
 pub struct RollbackProjectSecretToVersionResponse {
@@ -471,7 +1283,7 @@ pub struct RollbackProjectSecretToVersionResponse {
 pub struct EncryptedSecret {
     #[serde(alias = "_id")]
     pub id: String,
-    pub version: u8,
+    pub version: u32,
     pub workspace: String,
     #[serde(alias = "type")]
     pub type_name: String,
@@ -492,40 +1304,107 @@ pub struct SecretTag {
     slug: String,
 }
 
+/// The prefix that marks a secret value as binary data wrapped in base64
+///
+/// Infisical only stores text, so binary values such as certificates or keystores are stored as
+/// an [RFC 2397] data URL: this prefix followed by the standard base64 encoding of the bytes, with
+/// padding and without line breaks. The value stays printable, so other Infisical clients can
+/// export it as an environment variable, and it can be decoded by stripping the prefix and
+/// decoding the rest as base64.
+///
+/// Text values that happen to start with `base64:`, such as Laravel's `APP_KEY`, are never
+/// mistaken for binary ones. A text value that is itself such a data URL is read as the binary
+/// data it encodes.
+///
+/// [RFC 2397]: https://www.rfc-editor.org/rfc/rfc2397
+pub const BINARY_VALUE_PREFIX: &str = "data:application/octet-stream;base64,";
+
+/// Wraps binary data in base64 with the [`BINARY_VALUE_PREFIX`] so it can be stored as a secret
+/// value
+pub fn wrap_binary_value(value: &[u8]) -> SecretString {
+    SecretString::new(format!("{}{}", BINARY_VALUE_PREFIX, base64::encode(value)))
+}
+
 #[derive(Debug)]
 pub struct DecryptedSecret {
     pub id: String,
-    pub version: u8,
+    pub version: u32,
     pub workspace: String,
     pub type_name: String,
     pub key: String,
-    pub value: String,
+    pub value: SecretString,
     pub comment: Option<String>,
     pub audit: Audit,
 }
 
+impl DecryptedSecret {
+    /// Returns true if the value is binary data wrapped with [`wrap_binary_value`]
+    pub fn is_binary(&self) -> bool {
+        self.value.expose().starts_with(BINARY_VALUE_PREFIX)
+    }
+
+    /// Returns the raw bytes of the value
+    ///
+    /// Binary values wrapped with [`wrap_binary_value`] are unwrapped, any other value is returned
+    /// as its UTF-8 bytes.
+    pub fn value_bytes(&self) -> Result<SecretBytes> {
+        match self.value.expose().strip_prefix(BINARY_VALUE_PREFIX) {
+            Some(encoded) => base64::decode("value", encoded).map(SecretBytes::new),
+            None => Ok(SecretBytes::from(self.value.expose().as_bytes())),
+        }
+    }
+}
+
+impl EncryptedKey {
+    /// Decrypts the key alone, without the value and comment of its secret
+    pub fn decrypt(&self, project_key: &ProjectKey) -> Result<String> {
+        project_key.decrypt(&self.ciphertext, &self.iv, &self.tag)
+    }
+}
+
+impl EncryptedValue {
+    /// Encrypts a binary value, wrapped with [`wrap_binary_value`] so it is stored as text
+    pub fn encrypt_binary(value: &[u8], project_key: &ProjectKey) -> Result<EncryptedValue> {
+        let wrapped = wrap_binary_value(value);
+
+        Ok(project_key.encrypt(wrapped.expose())?.into())
+    }
+
+    /// Encrypts raw bytes as they are, without wrapping them
+    ///
+    /// Only use this if every client reading the secret expects raw bytes, since Infisical and
+    /// most of its clients expect secret values to be UTF-8 text.
+    pub fn encrypt_bytes(value: &[u8], project_key: &ProjectKey) -> Result<EncryptedValue> {
+        Ok(aes256gcm::encrypt_bytes(value, &project_key.aes_key()?)?.into())
+    }
+
+    /// Decrypts the value to raw bytes, without requiring it to be UTF-8 or unwrapping it
+    pub fn decrypt_bytes(&self, project_key: &ProjectKey) -> Result<SecretBytes> {
+        aes256gcm::decrypt_bytes(
+            &self.ciphertext,
+            &self.iv,
+            &self.tag,
+            &project_key.aes_key()?,
+        )
+        .map(SecretBytes::new)
+    }
+}
+
 impl EncryptedSecret {
-    pub fn decrypt(secret: &EncryptedSecret, private_key: &str) -> Result<DecryptedSecret> {
+    pub fn decrypt(secret: &EncryptedSecret, project_key: &ProjectKey) -> Result<DecryptedSecret> {
         let mut comment = None::<String>;
-        let key = decrypt(
-            &secret.key.ciphertext,
-            &secret.key.iv,
-            &secret.key.tag,
-            private_key,
-        )?;
-        let value = decrypt(
+        let key = project_key.decrypt(&secret.key.ciphertext, &secret.key.iv, &secret.key.tag)?;
+        let value = project_key.decrypt(
             &secret.value.ciphertext,
             &secret.value.iv,
             &secret.value.tag,
-            private_key,
         )?;
 
         if let Some(encrypted_comment) = &secret.comment {
-            comment = Some(decrypt(
+            comment = Some(project_key.decrypt(
                 &encrypted_comment.ciphertext,
                 &encrypted_comment.iv,
                 &encrypted_comment.tag,
-                private_key,
             )?);
         }
 
@@ -535,11 +1414,66 @@ impl EncryptedSecret {
             workspace: secret.workspace.clone(),
             type_name: secret.type_name.clone(),
             key,
-            value,
+            value: value.into(),
             comment,
             audit: secret.audit.clone(),
         })
     }
+
+    /// Decrypts a batch of secrets, collecting the secrets that fail to decrypt instead of
+    /// stopping at the first failure
+    ///
+    /// With the `rayon` feature enabled, the secrets are decrypted in parallel. The order of the
+    /// secrets is preserved either way.
+    pub fn decrypt_batch(
+        secrets: &[EncryptedSecret],
+        project_key: &ProjectKey,
+    ) -> DecryptedSecrets {
+        #[cfg(feature = "rayon")]
+        let results: Vec<_> = {
+            use rayon::prelude::*;
+
+            secrets
+                .par_iter()
+                .map(|secret| (secret, EncryptedSecret::decrypt(secret, project_key)))
+                .collect()
+        };
+        #[cfg(not(feature = "rayon"))]
+        let results = secrets
+            .iter()
+            .map(|secret| (secret, EncryptedSecret::decrypt(secret, project_key)));
+
+        let mut decrypted = DecryptedSecrets::default();
+        for (secret, result) in results {
+            match result {
+                Ok(secret) => decrypted.secrets.push(secret),
+                Err(error) => decrypted.failures.push(SecretDecryptionFailure {
+                    id: secret.id.clone(),
+                    error,
+                }),
+            }
+        }
+
+        decrypted
+    }
+}
+
+/// The result of decrypting a batch of secrets with [`EncryptedSecret::decrypt_batch`]
+#[derive(Debug, Default)]
+pub struct DecryptedSecrets {
+    /// The secrets that were decrypted
+    pub secrets: Vec<DecryptedSecret>,
+    /// The secrets that could not be decrypted
+    pub failures: Vec<SecretDecryptionFailure>,
+}
+
+/// A secret that could not be decrypted
+#[derive(Debug)]
+pub struct SecretDecryptionFailure {
+    /// The id of the secret
+    pub id: String,
+    /// The reason the secret could not be decrypted
+    pub error: crate::Error,
 }
 
 #[derive(Deserialize, Serialize)]
@@ -654,3 +1588,322 @@ pub struct ErrorResponse {
     pub application: String,
     pub extra: Vec<String>,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    const PRIVATE_KEY: &str = "QkJCQkJCQkJCQkJCQkJCQkJCQkJCQkJCQkJCQkJCQkI=";
+
+    fn user(fields: Value) -> User {
+        let mut user = json!({
+            "_id": "6420a3b4c5d6e7f8a9b0c1d2",
+            "email": "jane@example.com",
+            "firstName": "Jane",
+            "lastName": "Doe",
+            "publicKey": "AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA=",
+            "salt": "4f7e1f0c9a2b3d5e6f708192a3b4c5d6",
+            "__v": 0,
+            "devices": [],
+            "isMfaEnabled": false,
+            "mfaMethods": [],
+            "updatedAt": "2023-04-01T00:00:00.000Z",
+            "createdAt": "2023-04-01T00:00:00.000Z",
+        });
+        user.as_object_mut()
+            .unwrap()
+            .extend(fields.as_object().unwrap().clone());
+
+        serde_json::from_value(user).unwrap()
+    }
+
+    fn version_2_user() -> User {
+        user(json!({
+            "encryptionVersion": 2,
+            "protectedKey": "Mosy+G0/0pysvz2GKyDItao5taw8fNLgIy1urPOaeTgoNweBuUe/fX5+i+E+up+ivwxnCPaaMzVePxc6KDz0xQ==",
+            "protectedKeyIV": "AQEBAQEBAQEBAQEBAQEBAQ==",
+            "protectedKeyTag": "hdBhXd4Fvwi5BkKfZ4oztw==",
+            "encryptedPrivateKey": "7R1rTZZbA4kwguXe1xfoKMAKdySvCeBDWmS+hwjW+lVxQAvsUgaVm+BRyr4=",
+            "iv": "AgICAgICAgICAgICAgICAg==",
+            "tag": "XobOw20je6YXJiYO049USw==",
+        }))
+    }
+
+    fn encrypted_secret(id: &str, value: &str, project_key: &ProjectKey) -> EncryptedSecret {
+        let key = project_key.encrypt("KEY").unwrap();
+        let value = project_key.encrypt(value).unwrap();
+
+        serde_json::from_value(json!({
+            "_id": id,
+            "version": 1,
+            "workspace": "workspace-id",
+            "type": "shared",
+            "secretKeyCiphertext": key.text,
+            "secretKeyIV": key.nonce,
+            "secretKeyTag": key.tag,
+            "secretValueCiphertext": value.text,
+            "secretValueIV": value.nonce,
+            "secretValueTag": value.tag,
+            "createdAt": "2023-04-01T00:00:00.000Z",
+            "updatedAt": "2023-04-01T00:00:00.000Z",
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn message_only_responses_are_told_apart_from_errors() {
+        let error = json!({
+            "type": "unauthorized",
+            "message": "Failed to upload key",
+            "context": {},
+            "level": 30,
+            "level_name": "INFO",
+            "status_code": 401,
+            "datetime_iso": "2023-04-01T00:00:00.000Z",
+            "application": "infisical",
+            "extra": [],
+        });
+        let success = json!({
+            "message": "Successfully uploaded key to workspace",
+            "key": { "workspace": "workspace-id" },
+        });
+
+        assert!(matches!(
+            serde_json::from_value(error).unwrap(),
+            ApiResponse::<UploadProjectKeyResponse>::Err(_)
+        ));
+        assert!(matches!(
+            serde_json::from_value(success).unwrap(),
+            ApiResponse::<UploadProjectKeyResponse>::Ok(_)
+        ));
+    }
+
+    #[test]
+    fn decrypt_batch_reports_each_failure() {
+        let project_key = ProjectKey::generate();
+        let secrets = vec![
+            encrypted_secret("first", "one", &project_key),
+            encrypted_secret("corrupted", "two", &ProjectKey::generate()),
+            encrypted_secret("third", "three", &project_key),
+        ];
+
+        let decrypted = EncryptedSecret::decrypt_batch(&secrets, &project_key);
+
+        let values: Vec<_> = decrypted
+            .secrets
+            .iter()
+            .map(|secret| secret.value.expose())
+            .collect();
+        assert_eq!(values, ["one", "three"]);
+        assert_eq!(decrypted.failures.len(), 1);
+        assert_eq!(decrypted.failures[0].id, "corrupted");
+        assert!(decrypted.failures[0].error.is_decrypt());
+    }
+
+    #[test]
+    fn binary_value_round_trips() {
+        let project_key = ProjectKey::generate();
+        let certificate = [0x30, 0x82, 0xff, 0x00, 0xfe];
+        let value = EncryptedValue::encrypt_binary(&certificate, &project_key).unwrap();
+
+        let mut secret: EncryptedSecret = serde_json::from_value(json!({
+            "_id": "certificate",
+            "version": 1,
+            "workspace": "workspace-id",
+            "type": "shared",
+            "secretKeyCiphertext": "",
+            "secretKeyIV": "",
+            "secretKeyTag": "",
+            "secretValueCiphertext": "",
+            "secretValueIV": "",
+            "secretValueTag": "",
+            "createdAt": "2023-04-01T00:00:00.000Z",
+            "updatedAt": "2023-04-01T00:00:00.000Z",
+        }))
+        .unwrap();
+        secret.key = project_key.encrypt("CERTIFICATE").unwrap().into();
+        secret.value = value;
+
+        let decrypted = EncryptedSecret::decrypt(&secret, &project_key).unwrap();
+
+        assert!(decrypted.is_binary());
+        assert_eq!(
+            decrypted.value.expose(),
+            "data:application/octet-stream;base64,MIL/AP4="
+        );
+        assert_eq!(decrypted.value_bytes().unwrap().expose(), certificate);
+    }
+
+    #[test]
+    fn base64_prefixed_text_is_not_binary() {
+        let project_key = ProjectKey::generate();
+        let app_key = "base64:2fl+Ktvkfl+Fuz4Qp/A75G2RTiWVA/ZoKZvp6fiiM10=";
+        let secret = encrypted_secret("app-key", app_key, &project_key);
+
+        let decrypted = EncryptedSecret::decrypt(&secret, &project_key).unwrap();
+
+        assert!(!decrypted.is_binary());
+        assert_eq!(decrypted.value.expose(), app_key);
+        assert_eq!(
+            decrypted.value_bytes().unwrap().expose(),
+            app_key.as_bytes()
+        );
+    }
+
+    #[test]
+    fn text_value_bytes_are_utf8() {
+        let project_key = ProjectKey::generate();
+        let secret = encrypted_secret("text", "hello", &project_key);
+
+        let decrypted = EncryptedSecret::decrypt(&secret, &project_key).unwrap();
+
+        assert!(!decrypted.is_binary());
+        assert_eq!(decrypted.value_bytes().unwrap().expose(), b"hello");
+    }
+
+    #[test]
+    fn raw_bytes_round_trip() {
+        let project_key = ProjectKey::generate();
+        let value = EncryptedValue::encrypt_bytes(&[0xff, 0xfe], &project_key).unwrap();
+
+        assert_eq!(
+            value.decrypt_bytes(&project_key).unwrap().expose(),
+            [0xff, 0xfe]
+        );
+    }
+
+    #[test]
+    fn environment_slugs_are_validated() {
+        for slug in ["dev", "staging-2", "preview_eu", "a"] {
+            assert!(Environment::validate_slug(slug).is_ok(), "{}", slug);
+        }
+
+        for slug in [
+            "",
+            "Prod",
+            "dev env",
+            "-dev",
+            "dev_",
+            "dév",
+            &"a".repeat(65),
+        ] {
+            assert!(
+                Environment::validate_slug(slug)
+                    .unwrap_err()
+                    .is_invalid_input(),
+                "{}",
+                slug
+            );
+        }
+    }
+
+    #[test]
+    fn decrypts_version_1_private_key() {
+        let user = user(json!({
+            "encryptionVersion": 1,
+            "encryptedPrivateKey": "Cmmdl0BoMkdKBLDNFoXThB42UWCMQiJCSXPVxz6Y+co3Zl9s9G6Klow5Qqw=",
+            "iv": "AwMDAwMDAwMDAwMDAwMDAw==",
+            "tag": "KGOR5GB1NotXF1+uBEMVlw==",
+        }));
+
+        let private_key = user.decrypt_private_key("hunter2").unwrap();
+
+        assert_eq!(private_key.to_base64().expose(), PRIVATE_KEY);
+    }
+
+    #[test]
+    fn decrypts_version_2_private_key() {
+        let private_key = version_2_user()
+            .decrypt_private_key("correct horse battery staple")
+            .unwrap();
+
+        assert_eq!(private_key.to_base64().expose(), PRIVATE_KEY);
+    }
+
+    #[test]
+    fn version_2_rejects_wrong_password() {
+        let err = version_2_user().decrypt_private_key("hunter2").unwrap_err();
+
+        assert!(err.is_decrypt());
+    }
+
+    #[test]
+    fn version_2_requires_protected_key() {
+        let mut user = version_2_user();
+        user.protected_key = None;
+
+        let err = user
+            .decrypt_private_key("correct horse battery staple")
+            .unwrap_err();
+
+        assert!(err.is_decode());
+        assert!(err.to_string().contains("protectedKey"));
+    }
+
+    #[test]
+    fn roles_round_trip_as_strings() {
+        let roles: Vec<Role> =
+            serde_json::from_value(json!(["admin", "no-access", "billing"])).unwrap();
+
+        assert_eq!(
+            roles,
+            [
+                Role::Admin,
+                Role::NoAccess,
+                Role::Other("billing".to_string())
+            ]
+        );
+        assert_eq!(
+            serde_json::to_value(&roles).unwrap(),
+            json!(["admin", "no-access", "billing"])
+        );
+    }
+
+    #[test]
+    fn membership_statuses_round_trip_as_strings() {
+        let status: MembershipStatus = serde_json::from_value(json!("invited")).unwrap();
+        assert_eq!(status, MembershipStatus::Invited);
+
+        let status: MembershipStatus = serde_json::from_value(json!("suspended")).unwrap();
+        assert_eq!(status.to_string(), "suspended");
+    }
+
+    #[test]
+    fn denied_permissions_are_typed() {
+        let membership: ProjectMembership = serde_json::from_value(json!({
+            "_id": "membership-id",
+            "role": "member",
+            "workspace": "workspace-id",
+            "deniedPermissions": [
+                { "_id": "permission-id", "ability": "write", "environmentSlug": "staging" },
+                { "ability": "read", "environmentSlug": "prod" },
+            ],
+            "user": {
+                "_id": "user-id",
+                "email": "jane@example.com",
+                "firstName": "Jane",
+                "lastName": "Doe",
+                "__v": 0,
+                "devices": [],
+                "isMfaEnabled": false,
+                "mfaMethods": [],
+                "createdAt": "2023-04-01T00:00:00.000Z",
+                "updatedAt": "2023-04-01T00:00:00.000Z",
+            },
+            "createdAt": "2023-04-01T00:00:00.000Z",
+            "updatedAt": "2023-04-01T00:00:00.000Z",
+        }))
+        .unwrap();
+
+        assert_eq!(
+            membership.denied_permissions,
+            [
+                DeniedPermission::write("staging"),
+                DeniedPermission::read("prod")
+            ]
+        );
+        assert!(membership.is_denied(&Ability::Write, "staging"));
+        assert!(!membership.is_denied(&Ability::Read, "staging"));
+    }
+}
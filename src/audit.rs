@@ -0,0 +1,505 @@
+//! Human-readable change events from the logs of a project
+//!
+//! The logs of a project only record the ids of the secret versions an action went from and to.
+//! [`Client::get_change_events`] resolves those versions through the secret versions endpoint and
+//! decrypts them into [`ChangeEvent`]s, which display as e.g.
+//! `DATABASE_URL changed by jane@example.com at 2023-04-01T00:00:00Z from env prod`.
+//!
+//! Values can be redacted to keyed HMAC-SHA256 digests with [`Redaction`], which still shows
+//! whether a value changed without revealing it. Without the key, a digest can't be reversed by
+//! hashing guesses of the value.
+//!
+//! [`Client::get_change_events`]: crate::Client::get_change_events
+
+use std::collections::HashMap;
+use std::fmt;
+
+use aes_gcm::aead::{rand_core::RngCore, OsRng};
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use time::format_description::well_known::Rfc3339;
+use time::OffsetDateTime;
+use zeroize::Zeroize;
+
+use crate::api::models::{LogAction, ProjectLog, SecretDecryptionFailure, SecretVersion};
+use crate::error::Result;
+use crate::keys::ProjectKey;
+use crate::secret::SecretString;
+use crate::utils::hex;
+
+/// The key values are redacted with by [`Redaction::Hmac`]
+///
+/// The key is zeroized on drop and redacted when formatted with `Debug`.
+#[derive(Clone)]
+pub struct RedactionKey([u8; 32]);
+
+impl RedactionKey {
+    /// Generates a random key
+    pub fn generate() -> RedactionKey {
+        let mut bytes = [0; 32];
+        OsRng.fill_bytes(&mut bytes);
+
+        RedactionKey(bytes)
+    }
+
+    /// Constructs a `RedactionKey` from its raw bytes
+    pub fn from_bytes(bytes: [u8; 32]) -> RedactionKey {
+        RedactionKey(bytes)
+    }
+
+    fn digest(&self, value: &str) -> String {
+        let mut mac = <Hmac<Sha256> as Mac>::new_from_slice(&self.0)
+            .expect("HMAC accepts keys of any length");
+        mac.update(value.as_bytes());
+
+        hex::encode(&mac.finalize().into_bytes())
+    }
+}
+
+impl Drop for RedactionKey {
+    fn drop(&mut self) {
+        self.0.zeroize();
+    }
+}
+
+impl fmt::Debug for RedactionKey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("RedactionKey([REDACTED])")
+    }
+}
+
+/// How secret values are included in change events
+#[derive(Clone, Debug, Default)]
+pub enum Redaction {
+    /// Values are included as they are
+    None,
+    /// Values are replaced by the hexadecimal HMAC-SHA256 digest of their UTF-8 bytes with the
+    /// given key, so that digests can be compared across calls made with the same key
+    Hmac(RedactionKey),
+    /// Values are replaced by the hexadecimal HMAC-SHA256 digest of their UTF-8 bytes with a key
+    /// generated for each call, so that digests can only be compared within the events of a call
+    #[default]
+    RandomHmac,
+}
+
+/// The value of a secret before or after a change
+#[derive(Clone, Debug)]
+pub enum ChangeValue {
+    /// The decrypted value
+    Plain(SecretString),
+    /// The hexadecimal HMAC-SHA256 digest of the value
+    Hmac(String),
+}
+
+impl ChangeValue {
+    fn new(value: &SecretString, key: Option<&RedactionKey>) -> ChangeValue {
+        match key {
+            None => ChangeValue::Plain(value.clone()),
+            Some(key) => ChangeValue::Hmac(key.digest(value.expose())),
+        }
+    }
+}
+
+impl PartialEq for ChangeValue {
+    fn eq(&self, other: &ChangeValue) -> bool {
+        match (self, other) {
+            (ChangeValue::Plain(a), ChangeValue::Plain(b)) => a.expose() == b.expose(),
+            (ChangeValue::Hmac(a), ChangeValue::Hmac(b)) => a == b,
+            _ => false,
+        }
+    }
+}
+
+/// A change to a single secret recorded in the logs of a project
+#[derive(Clone, Debug, PartialEq)]
+pub struct ChangeEvent {
+    /// The id of the log the change was recorded in
+    pub log_id: String,
+    /// The action that made the change
+    pub action: LogAction,
+    /// The email of the user who made the change
+    pub actor: String,
+    /// When the change was made
+    pub at: OffsetDateTime,
+    /// The slug of the environment of the secret
+    pub environment: String,
+    /// The key of the secret after the change, or before it if the secret was deleted
+    pub key: String,
+    /// The key of the secret before the change, if the change renamed it
+    pub old_key: Option<String>,
+    /// The value before the change, missing for added secrets
+    pub old_value: Option<ChangeValue>,
+    /// The value after the change, missing for deleted secrets
+    pub new_value: Option<ChangeValue>,
+}
+
+impl fmt::Display for ChangeEvent {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let verb = match &self.action {
+            LogAction::AddSecrets => "added",
+            LogAction::UpdateSecrets => "changed",
+            LogAction::ReadSecrets => "read",
+            LogAction::DeleteSecrets => "deleted",
+            LogAction::Other(action) => action,
+        };
+        let at = self.at.format(&Rfc3339).map_err(|_| fmt::Error)?;
+
+        write!(
+            f,
+            "{} {} by {} at {} from env {}",
+            self.key, verb, self.actor, at, self.environment
+        )
+    }
+}
+
+/// The change events of a set of logs, returned by [`Client::get_change_events`]
+///
+/// [`Client::get_change_events`]: crate::Client::get_change_events
+#[derive(Debug, Default)]
+pub struct ChangeEvents {
+    /// The events, in the order of the logs
+    pub events: Vec<ChangeEvent>,
+    /// The ids of the secret versions that couldn't be found, e.g. because their secret has since
+    /// been deleted or the log doesn't name it
+    ///
+    /// Changes involving them are left out of `events`.
+    pub unresolved: Vec<String>,
+    /// The secret versions that couldn't be decrypted, by version id
+    ///
+    /// Changes involving them are left out of `events`.
+    pub failures: Vec<SecretDecryptionFailure>,
+}
+
+/// Returns the ids of the secret versions referenced by `logs`
+fn version_ids(logs: &[ProjectLog]) -> Vec<&str> {
+    logs.iter()
+        .flat_map(|log| &log.actions)
+        .flat_map(|action| &action.payload)
+        .flat_map(|payload| [&payload.old_secret_version, &payload.new_secret_version])
+        .flatten()
+        .map(String::as_str)
+        .collect()
+}
+
+/// Returns the ids of the secrets named by `logs` along with the ids of their versions the logs
+/// reference
+pub(crate) fn secret_versions(logs: &[ProjectLog]) -> Vec<(&str, &str)> {
+    logs.iter()
+        .flat_map(|log| &log.actions)
+        .flat_map(|action| &action.payload)
+        .filter_map(|payload| {
+            let secret = payload.secret.as_deref()?;
+            Some(
+                [&payload.old_secret_version, &payload.new_secret_version]
+                    .into_iter()
+                    .flatten()
+                    .map(move |version| (secret, version.as_str())),
+            )
+        })
+        .flatten()
+        .collect()
+}
+
+fn decrypt_version(
+    version: &SecretVersion,
+    project_key: &ProjectKey,
+) -> Result<(String, SecretString)> {
+    let key = project_key.decrypt(&version.key.ciphertext, &version.key.iv, &version.key.tag)?;
+    let value = project_key.decrypt(
+        &version.value.ciphertext,
+        &version.value.iv,
+        &version.value.tag,
+    )?;
+
+    Ok((key, value.into()))
+}
+
+/// Builds the change events of `logs` from the secret versions they reference
+pub(crate) fn change_events(
+    logs: &[ProjectLog],
+    versions: &HashMap<String, SecretVersion>,
+    project_key: &ProjectKey,
+    redaction: &Redaction,
+) -> ChangeEvents {
+    let redaction_key = match redaction {
+        Redaction::None => None,
+        Redaction::Hmac(key) => Some(key.clone()),
+        Redaction::RandomHmac => Some(RedactionKey::generate()),
+    };
+    let mut events = ChangeEvents::default();
+
+    let mut decrypted = HashMap::new();
+    for id in version_ids(logs) {
+        let Some(version) = versions.get(id) else {
+            continue;
+        };
+        if decrypted.contains_key(id) || events.failures.iter().any(|f| f.id == id) {
+            continue;
+        }
+
+        match decrypt_version(version, project_key) {
+            Ok((key, value)) => {
+                decrypted.insert(id, (version, key, value));
+            }
+            Err(error) => events.failures.push(SecretDecryptionFailure {
+                id: id.to_string(),
+                error,
+            }),
+        }
+    }
+
+    for log in logs {
+        for action in &log.actions {
+            for payload in &action.payload {
+                let ids = [&payload.old_secret_version, &payload.new_secret_version];
+                let missing: Vec<_> = ids
+                    .into_iter()
+                    .flatten()
+                    .filter(|id| !versions.contains_key(*id))
+                    .collect();
+                if !missing.is_empty() {
+                    events.unresolved.extend(missing.into_iter().cloned());
+                    continue;
+                }
+                if ids
+                    .into_iter()
+                    .flatten()
+                    .any(|id| !decrypted.contains_key(id.as_str()))
+                {
+                    continue;
+                }
+
+                let old = payload
+                    .old_secret_version
+                    .as_deref()
+                    .map(|id| &decrypted[id]);
+                let new = payload
+                    .new_secret_version
+                    .as_deref()
+                    .map(|id| &decrypted[id]);
+                let Some((version, key, _)) = new.or(old) else {
+                    continue;
+                };
+                let old_key = match (old, new) {
+                    (Some((_, old_key, _)), Some((_, new_key, _))) if old_key != new_key => {
+                        Some(old_key.clone())
+                    }
+                    _ => None,
+                };
+
+                events.events.push(ChangeEvent {
+                    log_id: log.id.clone(),
+                    action: action.name.clone(),
+                    actor: log.user.email.clone(),
+                    at: log.audit.created_at,
+                    environment: version.environment.clone(),
+                    key: key.clone(),
+                    old_key,
+                    old_value: old
+                        .map(|(_, _, value)| ChangeValue::new(value, redaction_key.as_ref())),
+                    new_value: new
+                        .map(|(_, _, value)| ChangeValue::new(value, redaction_key.as_ref())),
+                });
+            }
+        }
+    }
+
+    events
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn version(id: &str, key: &str, value: &str, project_key: &ProjectKey) -> SecretVersion {
+        let key = project_key.encrypt(key).unwrap();
+        let value = project_key.encrypt(value).unwrap();
+
+        serde_json::from_value(json!({
+            "_id": id,
+            "tags": [],
+            "secret": "secret-id",
+            "version": 1,
+            "workspace": "workspace-id",
+            "type": "shared",
+            "environment": "prod",
+            "isDeleted": false,
+            "secretKeyCiphertext": key.text,
+            "secretKeyIV": key.nonce,
+            "secretKeyTag": key.tag,
+            "secretValueCiphertext": value.text,
+            "secretValueIV": value.nonce,
+            "secretValueTag": value.tag,
+            "__v": 0,
+            "createdAt": "2023-04-01T00:00:00.000Z",
+            "updatedAt": "2023-04-01T00:00:00.000Z",
+        }))
+        .unwrap()
+    }
+
+    fn log(action: &str, payload: serde_json::Value) -> ProjectLog {
+        serde_json::from_value(json!({
+            "_id": "log-id",
+            "user": {
+                "_id": "user-id",
+                "email": "alice@example.com",
+                "firstName": "Alice",
+                "lastName": "Doe",
+                "__v": 0,
+                "devices": [],
+                "isMfaEnabled": false,
+                "mfaMethods": [],
+                "createdAt": "2023-04-01T00:00:00.000Z",
+                "updatedAt": "2023-04-01T00:00:00.000Z",
+            },
+            "workspace": "workspace-id",
+            "actionNames": [action],
+            "actions": [{
+                "name": action,
+                "user": "user-id",
+                "workspace": "workspace-id",
+                "payload": payload,
+            }],
+            "channel": "web",
+            "ipAddress": "127.0.0.1",
+            "createdAt": "2023-04-01T12:30:00.000Z",
+            "updatedAt": "2023-04-01T12:30:00.000Z",
+        }))
+        .unwrap()
+    }
+
+    fn versions(project_key: &ProjectKey) -> HashMap<String, SecretVersion> {
+        [
+            version("v1", "DATABASE_URL", "postgres://old", project_key),
+            version("v2", "DATABASE_URL", "postgres://new", project_key),
+        ]
+        .into_iter()
+        .map(|version| (version.id.clone(), version))
+        .collect()
+    }
+
+    #[test]
+    fn update_becomes_change_event() {
+        let project_key = ProjectKey::generate();
+        let logs = [log(
+            "updateSecrets",
+            json!([{
+                "secret": "secret-id",
+                "oldSecretVersion": "v1",
+                "newSecretVersion": "v2",
+            }]),
+        )];
+
+        let events = change_events(
+            &logs,
+            &versions(&project_key),
+            &project_key,
+            &Redaction::None,
+        );
+
+        assert_eq!(
+            secret_versions(&logs),
+            [("secret-id", "v1"), ("secret-id", "v2")]
+        );
+        assert!(events.unresolved.is_empty());
+        assert!(events.failures.is_empty());
+        let event = &events.events[0];
+        assert_eq!(
+            event.to_string(),
+            "DATABASE_URL changed by alice@example.com at 2023-04-01T12:30:00Z from env prod"
+        );
+        assert_eq!(
+            event.new_value,
+            Some(ChangeValue::Plain("postgres://new".into()))
+        );
+        assert_eq!(event.old_key, None);
+    }
+
+    #[test]
+    fn values_are_redacted_to_keyed_hashes() {
+        let project_key = ProjectKey::generate();
+        let logs = [log("addSecrets", json!([{ "newSecretVersion": "v2" }]))];
+        let redaction_key = RedactionKey::from_bytes([7; 32]);
+
+        let events = change_events(
+            &logs,
+            &versions(&project_key),
+            &project_key,
+            &Redaction::Hmac(redaction_key.clone()),
+        );
+
+        let event = &events.events[0];
+        assert!(event.to_string().starts_with("DATABASE_URL added by"));
+        assert_eq!(event.old_value, None);
+        assert_eq!(
+            event.new_value,
+            Some(ChangeValue::Hmac(redaction_key.digest("postgres://new")))
+        );
+        assert_ne!(
+            event.new_value,
+            Some(ChangeValue::Hmac(
+                RedactionKey::from_bytes([8; 32]).digest("postgres://new")
+            ))
+        );
+    }
+
+    #[test]
+    fn random_redaction_key_is_shared_within_a_call() {
+        let project_key = ProjectKey::generate();
+        let logs = [
+            log("addSecrets", json!([{ "newSecretVersion": "v2" }])),
+            log("readSecrets", json!([{ "oldSecretVersion": "v2" }])),
+        ];
+        let versions = versions(&project_key);
+
+        let first = change_events(&logs, &versions, &project_key, &Redaction::RandomHmac);
+        let second = change_events(&logs, &versions, &project_key, &Redaction::default());
+
+        assert_eq!(first.events[0].new_value, first.events[1].old_value);
+        assert_ne!(first.events[0].new_value, second.events[0].new_value);
+    }
+
+    #[test]
+    fn missing_versions_are_unresolved() {
+        let project_key = ProjectKey::generate();
+        let logs = [log(
+            "deleteSecrets",
+            json!([{ "oldSecretVersion": "deleted-version" }]),
+        )];
+
+        let events = change_events(
+            &logs,
+            &versions(&project_key),
+            &project_key,
+            &Redaction::None,
+        );
+
+        assert!(events.events.is_empty());
+        assert_eq!(events.unresolved, ["deleted-version"]);
+    }
+
+    #[test]
+    fn undecryptable_versions_are_reported_and_skipped() {
+        let project_key = ProjectKey::generate();
+        let mut versions = versions(&project_key);
+        let corrupted = version("v3", "API_KEY", "secret", &ProjectKey::generate());
+        versions.insert(corrupted.id.clone(), corrupted);
+        let logs = [log(
+            "updateSecrets",
+            json!([
+                { "oldSecretVersion": "v1", "newSecretVersion": "v2" },
+                { "newSecretVersion": "v3" },
+                { "oldSecretVersion": "v3" },
+            ]),
+        )];
+
+        let events = change_events(&logs, &versions, &project_key, &Redaction::None);
+
+        assert_eq!(events.events.len(), 1);
+        assert_eq!(events.events[0].key, "DATABASE_URL");
+        assert_eq!(events.failures.len(), 1);
+        assert_eq!(events.failures[0].id, "v3");
+        assert!(events.failures[0].error.is_decrypt());
+    }
+}
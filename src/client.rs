@@ -1,14 +1,29 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::future::Future;
+use std::pin::pin;
+use std::sync::Arc;
+use std::time::Instant;
+
 use crate::api;
+use crate::audit::{self, ChangeEvents, Redaction};
+use crate::envelope;
 use crate::error::Result;
+use crate::keys::{ProjectKey, PublicKey, UserPrivateKey};
+use crate::metrics::MetricsRecorder;
+use crate::pagination::{self, PageSize};
+use crate::reconcile::{self, Change, LiveProject, Plan, ProjectPlan, ProjectSpec, Spec};
+use crate::rotation::{RotationCheckpoint, RotationProgress, RotationReport};
+use crate::secret::{SecretBytes, SecretString};
 use crate::utils;
 
-use onionsalt::crypto;
+use futures::{Stream, TryStreamExt};
 use reqwest::header;
 
 /// `Client` provides a wrapper around the Infisical API that gives easy access to its endpoints
 pub struct Client {
     http_client: reqwest::Client,
     api_base: String,
+    metrics: Option<Arc<dyn MetricsRecorder>>,
 }
 
 impl Client {
@@ -29,9 +44,9 @@ impl Client {
             base_url: self.api_base.clone(),
         };
 
-        let response = api::get_my_user(&self.http_client, request)
-            .await
-            .map_err(crate::error::reqwest)?;
+        let response = self
+            .send("get_my_user", api::get_my_user(&self.http_client, request))
+            .await?;
 
         Ok(response.user)
     }
@@ -41,9 +56,12 @@ impl Client {
             base_url: self.api_base.clone(),
         };
 
-        let response = api::get_my_organizations(&self.http_client, request)
-            .await
-            .map_err(crate::error::reqwest)?;
+        let response = self
+            .send(
+                "get_my_organizations",
+                api::get_my_organizations(&self.http_client, request),
+            )
+            .await?;
 
         Ok(response.organizations)
     }
@@ -57,9 +75,12 @@ impl Client {
             organization_id: organization_id.to_string(),
         };
 
-        let response = api::get_organization_memberships(&self.http_client, request)
-            .await
-            .map_err(crate::error::reqwest)?;
+        let response = self
+            .send(
+                "get_organization_memberships",
+                api::get_organization_memberships(&self.http_client, request),
+            )
+            .await?;
 
         Ok(response.memberships)
     }
@@ -68,18 +89,21 @@ impl Client {
         &self,
         organization_id: &str,
         membership_id: &str,
-        role: &str,
+        role: api::models::Role,
     ) -> Result<api::models::OrganizationMembership> {
         let request = api::models::UpdateOrganizationMembershipRequest {
             base_url: self.api_base.clone(),
             organization_id: organization_id.to_string(),
             membership_id: membership_id.to_string(),
-            role: role.to_string(),
+            role,
         };
 
-        let response = api::update_organization_membership(&self.http_client, request)
-            .await
-            .map_err(crate::error::reqwest)?;
+        let response = self
+            .send(
+                "update_organization_membership",
+                api::update_organization_membership(&self.http_client, request),
+            )
+            .await?;
 
         Ok(response.membership)
     }
@@ -95,13 +119,99 @@ impl Client {
             membership_id: membership_id.to_string(),
         };
 
-        let response = api::delete_organization_membership(&self.http_client, request)
-            .await
-            .map_err(crate::error::reqwest)?;
+        let response = self
+            .send(
+                "delete_organization_membership",
+                api::delete_organization_membership(&self.http_client, request),
+            )
+            .await?;
 
         Ok(response.membership)
     }
 
+    /// Invites a user to an organization by email
+    ///
+    /// Returns the link to complete the signup when Infisical can't send emails, so it can be
+    /// passed on to the user some other way.
+    pub async fn invite_organization_member(
+        &self,
+        organization_id: &str,
+        email: &str,
+    ) -> Result<Option<String>> {
+        let request = api::models::InviteOrganizationMemberRequest {
+            base_url: self.api_base.clone(),
+            invitee_email: email.to_string(),
+            organization_id: organization_id.to_string(),
+        };
+
+        let response = self
+            .send(
+                "invite_organization_member",
+                api::invite_organization_member(&self.http_client, request),
+            )
+            .await?;
+
+        Ok(response.complete_invite_link)
+    }
+
+    /// Gets the invitations to an organization that haven't been accepted yet
+    pub async fn get_pending_organization_invitations(
+        &self,
+        organization_id: &str,
+    ) -> Result<Vec<api::models::OrganizationMembership>> {
+        let memberships = self.get_organization_memberships(organization_id).await?;
+
+        Ok(memberships
+            .into_iter()
+            .filter(api::models::OrganizationMembership::is_pending)
+            .collect())
+    }
+
+    /// Sends a pending invitation to an organization again
+    ///
+    /// Fails without sending anything if there is no pending invitation for `email`.
+    pub async fn resend_organization_invitation(
+        &self,
+        organization_id: &str,
+        email: &str,
+    ) -> Result<Option<String>> {
+        let pending = self
+            .get_pending_organization_invitations(organization_id)
+            .await?;
+        if !pending.iter().any(|m| m.email() == Some(email)) {
+            return Err(crate::error::not_found(format!(
+                "no pending invitation for {}",
+                email
+            )));
+        }
+
+        self.invite_organization_member(organization_id, email)
+            .await
+    }
+
+    /// Revokes a pending invitation to an organization
+    ///
+    /// Fails without deleting anything if the membership isn't a pending invitation, so that
+    /// members who already joined aren't removed by mistake.
+    pub async fn revoke_organization_invitation(
+        &self,
+        organization_id: &str,
+        membership_id: &str,
+    ) -> Result<api::models::OrganizationMembership> {
+        let pending = self
+            .get_pending_organization_invitations(organization_id)
+            .await?;
+        if !pending.iter().any(|m| m.id == membership_id) {
+            return Err(crate::error::not_found(format!(
+                "no pending invitation with id {}",
+                membership_id
+            )));
+        }
+
+        self.delete_organization_membership(organization_id, membership_id)
+            .await
+    }
+
     pub async fn get_organization_projects(
         &self,
         organization_id: &str,
@@ -111,13 +221,254 @@ impl Client {
             organization_id: organization_id.to_string(),
         };
 
-        let response = api::get_organization_projects(&self.http_client, request)
-            .await
-            .map_err(crate::error::reqwest)?;
+        let response = self
+            .send(
+                "get_organization_projects",
+                api::get_organization_projects(&self.http_client, request),
+            )
+            .await?;
 
         Ok(response.workspaces)
     }
 
+    pub async fn get_project(&self, workspace_id: &str) -> Result<api::models::Workspace> {
+        let request = api::models::GetProjectRequest {
+            base_url: self.api_base.clone(),
+            workspace_id: workspace_id.to_string(),
+        };
+
+        let response = self
+            .send("get_project", api::get_project(&self.http_client, request))
+            .await?;
+
+        Ok(response.workspace)
+    }
+
+    /// Creates a project in an organization along with a new project key
+    ///
+    /// The project key is encrypted for the current user with their `private_key` and uploaded, so
+    /// it can be retrieved with [`Client::get_decrypted_project_key`]. The new key is also
+    /// returned, so it can be used right away.
+    ///
+    /// If the project key can't be uploaded, the project is deleted again before the error is
+    /// returned, so no project is left behind that no one can decrypt.
+    pub async fn create_project(
+        &self,
+        organization_id: &str,
+        name: &str,
+        private_key: &UserPrivateKey,
+    ) -> Result<(api::models::Workspace, ProjectKey)> {
+        let request = api::models::CreateProjectRequest {
+            base_url: self.api_base.clone(),
+            workspace_name: name.to_string(),
+            organization_id: organization_id.to_string(),
+        };
+
+        let workspace = self
+            .send(
+                "create_project",
+                api::create_project(&self.http_client, request),
+            )
+            .await?
+            .workspace;
+
+        let project_key = ProjectKey::generate();
+        let shared = async {
+            let user = self.get_user().await?;
+            self.share_project_key(
+                &workspace.id,
+                &user.id,
+                &PublicKey::from_base64(&user.public_key)?,
+                &project_key,
+                private_key,
+            )
+            .await
+        }
+        .await;
+
+        if let Err(e) = shared {
+            // Without its key no one could use the project, so it is removed again. The error of
+            // the upload is more useful to the caller than one from the removal, if that fails too.
+            let _ = self.delete_project(&workspace.id).await;
+            return Err(e);
+        }
+
+        Ok((workspace, project_key))
+    }
+
+    pub async fn rename_project(
+        &self,
+        workspace_id: &str,
+        name: &str,
+    ) -> Result<api::models::Workspace> {
+        let request = api::models::RenameProjectRequest {
+            base_url: self.api_base.clone(),
+            workspace_id: workspace_id.to_string(),
+            name: name.to_string(),
+        };
+
+        let response = self
+            .send(
+                "rename_project",
+                api::rename_project(&self.http_client, request),
+            )
+            .await?;
+
+        Ok(response.workspace)
+    }
+
+    pub async fn delete_project(&self, workspace_id: &str) -> Result<api::models::Workspace> {
+        let request = api::models::DeleteProjectRequest {
+            base_url: self.api_base.clone(),
+            workspace_id: workspace_id.to_string(),
+        };
+
+        let response = self
+            .send(
+                "delete_project",
+                api::delete_project(&self.http_client, request),
+            )
+            .await?;
+
+        Ok(response.workspace)
+    }
+
+    /// Adds an environment to a project
+    ///
+    /// The slug is validated with [`Environment::validate_slug`](api::models::Environment::validate_slug)
+    /// before any request is made.
+    pub async fn create_environment(
+        &self,
+        workspace_id: &str,
+        name: &str,
+        slug: &str,
+    ) -> Result<api::models::Environment> {
+        api::models::Environment::validate_slug(slug)?;
+
+        let request = api::models::CreateEnvironmentRequest {
+            base_url: self.api_base.clone(),
+            workspace_id: workspace_id.to_string(),
+            environment_name: name.to_string(),
+            environment_slug: slug.to_string(),
+        };
+
+        let response = self
+            .send(
+                "create_environment",
+                api::create_environment(&self.http_client, request),
+            )
+            .await?;
+
+        Ok(response.environment)
+    }
+
+    /// Changes the name and slug of the environment with the slug `slug`
+    ///
+    /// The new slug is validated with
+    /// [`Environment::validate_slug`](api::models::Environment::validate_slug) before any request
+    /// is made.
+    pub async fn rename_environment(
+        &self,
+        workspace_id: &str,
+        slug: &str,
+        new_name: &str,
+        new_slug: &str,
+    ) -> Result<api::models::Environment> {
+        api::models::Environment::validate_slug(new_slug)?;
+
+        let request = api::models::UpdateEnvironmentRequest {
+            base_url: self.api_base.clone(),
+            workspace_id: workspace_id.to_string(),
+            old_environment_slug: slug.to_string(),
+            environment_name: new_name.to_string(),
+            environment_slug: new_slug.to_string(),
+        };
+
+        let response = self
+            .send(
+                "update_environment",
+                api::update_environment(&self.http_client, request),
+            )
+            .await?;
+
+        Ok(response.environment)
+    }
+
+    /// Reorders the environments of a project to match the order of `slugs`
+    ///
+    /// `slugs` must list every environment of the project exactly once. Infisical can only swap
+    /// two environments at a time, so this makes one request per environment that is out of
+    /// place. Returns the environments in their new order.
+    pub async fn reorder_environments(
+        &self,
+        workspace_id: &str,
+        slugs: &[&str],
+    ) -> Result<Vec<api::models::Environment>> {
+        let mut environments = self.get_project(workspace_id).await?.environments;
+
+        let mut current: Vec<&str> = environments.iter().map(|e| e.slug.as_str()).collect();
+        let mut wanted = slugs.to_vec();
+        current.sort_unstable();
+        wanted.sort_unstable();
+        if current != wanted {
+            return Err(crate::error::invalid_input(
+                "environment order",
+                "must list every environment of the project exactly once",
+            ));
+        }
+
+        for (index, slug) in slugs.iter().enumerate() {
+            // The environments before index are already in place, and slugs was checked above to
+            // contain exactly the project's environments, so the slug is always found
+            let position = environments[index..]
+                .iter()
+                .position(|e| e.slug == *slug)
+                .map_or(index, |position| position + index);
+            if position == index {
+                continue;
+            }
+
+            let request = api::models::ReorderEnvironmentsRequest {
+                base_url: self.api_base.clone(),
+                workspace_id: workspace_id.to_string(),
+                environment_slug: environments[index].slug.clone(),
+                environment_name: environments[index].name.clone(),
+                other_environment_slug: environments[position].slug.clone(),
+                other_environment_name: environments[position].name.clone(),
+            };
+
+            self.send(
+                "reorder_environments",
+                api::reorder_environments(&self.http_client, request),
+            )
+            .await?;
+            environments.swap(index, position);
+        }
+
+        Ok(environments)
+    }
+
+    pub async fn delete_environment(
+        &self,
+        workspace_id: &str,
+        slug: &str,
+    ) -> Result<api::models::Environment> {
+        let request = api::models::DeleteEnvironmentRequest {
+            base_url: self.api_base.clone(),
+            workspace_id: workspace_id.to_string(),
+            environment_slug: slug.to_string(),
+        };
+
+        let response = self
+            .send(
+                "delete_environment",
+                api::delete_environment(&self.http_client, request),
+            )
+            .await?;
+
+        Ok(response.environment)
+    }
+
     pub async fn get_project_memberships(
         &self,
         workspace_id: &str,
@@ -127,9 +478,12 @@ impl Client {
             workspace_id: workspace_id.to_string(),
         };
 
-        let response = api::get_project_memberships(&self.http_client, request)
-            .await
-            .map_err(crate::error::reqwest)?;
+        let response = self
+            .send(
+                "get_project_memberships",
+                api::get_project_memberships(&self.http_client, request),
+            )
+            .await?;
 
         Ok(response.memberships)
     }
@@ -138,18 +492,21 @@ impl Client {
         &self,
         workspace_id: &str,
         membership_id: &str,
-        role: &str,
+        role: api::models::Role,
     ) -> Result<api::models::ProjectMembership> {
         let request = api::models::UpdateProjectMembershipRequest {
             base_url: self.api_base.clone(),
             workspace_id: workspace_id.to_string(),
             membership_id: membership_id.to_string(),
-            role: role.to_string(),
+            role,
         };
 
-        let response = api::update_project_membership(&self.http_client, request)
-            .await
-            .map_err(crate::error::reqwest)?;
+        let response = self
+            .send(
+                "update_project_membership",
+                api::update_project_membership(&self.http_client, request),
+            )
+            .await?;
 
         Ok(response.membership)
     }
@@ -165,13 +522,179 @@ impl Client {
             membership_id: membership_id.to_string(),
         };
 
-        let response = api::delete_project_membership(&self.http_client, request)
-            .await
-            .map_err(crate::error::reqwest)?;
+        let response = self
+            .send(
+                "delete_project_membership",
+                api::delete_project_membership(&self.http_client, request),
+            )
+            .await?;
 
         Ok(response.membership)
     }
 
+    /// Replaces the abilities denied to a project member
+    ///
+    /// Everything not denied is allowed by the member's role. For example, a member who may only
+    /// read the secrets of `staging` is denied writing to `staging` and both reading and writing
+    /// to every other environment. An empty list lifts all restrictions.
+    pub async fn set_denied_permissions(
+        &self,
+        membership_id: &str,
+        permissions: Vec<api::models::DeniedPermission>,
+    ) -> Result<Vec<api::models::DeniedPermission>> {
+        let request = api::models::SetDeniedPermissionsRequest {
+            base_url: self.api_base.clone(),
+            membership_id: membership_id.to_string(),
+            permissions,
+        };
+
+        let response = self
+            .send(
+                "set_denied_permissions",
+                api::set_denied_permissions(&self.http_client, request),
+            )
+            .await?;
+
+        Ok(response.permissions_denied)
+    }
+
+    /// Gets the roles of an organization, or of a project if `workspace_id` is given
+    ///
+    /// The built-in roles are included along with the custom ones. The result can be used to
+    /// check permissions locally with [`Rbac`](crate::rbac::Rbac).
+    pub async fn get_roles(
+        &self,
+        organization_id: &str,
+        workspace_id: Option<&str>,
+    ) -> Result<Vec<api::models::CustomRole>> {
+        let request = api::models::GetRolesRequest {
+            base_url: self.api_base.clone(),
+            org_id: organization_id.to_string(),
+            workspace_id: workspace_id.map(str::to_string),
+        };
+
+        let response = self
+            .send("get_roles", api::get_roles(&self.http_client, request))
+            .await?;
+
+        Ok(response.data.roles)
+    }
+
+    /// Creates a custom role in an organization, or in a project if `workspace_id` is given
+    pub async fn create_role(
+        &self,
+        organization_id: &str,
+        workspace_id: Option<&str>,
+        role: api::models::RoleDefinition,
+    ) -> Result<api::models::CustomRole> {
+        let request = api::models::CreateRoleRequest {
+            base_url: self.api_base.clone(),
+            org_id: organization_id.to_string(),
+            workspace_id: workspace_id.map(str::to_string),
+            role,
+        };
+
+        let response = self
+            .send("create_role", api::create_role(&self.http_client, request))
+            .await?;
+
+        Ok(response.role)
+    }
+
+    /// Replaces the name, slug, description and permissions of a custom role
+    pub async fn update_role(
+        &self,
+        role_id: &str,
+        organization_id: &str,
+        workspace_id: Option<&str>,
+        role: api::models::RoleDefinition,
+    ) -> Result<api::models::CustomRole> {
+        let request = api::models::UpdateRoleRequest {
+            base_url: self.api_base.clone(),
+            role_id: role_id.to_string(),
+            org_id: organization_id.to_string(),
+            workspace_id: workspace_id.map(str::to_string),
+            role,
+        };
+
+        let response = self
+            .send("update_role", api::update_role(&self.http_client, request))
+            .await?;
+
+        Ok(response.role)
+    }
+
+    /// Adds the user with the given email to a project and shares the project key with them
+    ///
+    /// The project key is encrypted for the public key of the new member using `private_key`, the
+    /// private key of the current user. The user must have completed their signup, since they
+    /// have no public key before that. See [`Client::add_project_member_by_user_id`] to add a
+    /// user by id instead.
+    pub async fn add_project_member(
+        &self,
+        workspace_id: &str,
+        email: &str,
+        project_key: &ProjectKey,
+        private_key: &UserPrivateKey,
+    ) -> Result<api::models::ProjectInvitee> {
+        let request = api::models::InviteProjectMemberRequest {
+            base_url: self.api_base.clone(),
+            workspace_id: workspace_id.to_string(),
+            email: email.to_string(),
+        };
+
+        let invitee = self
+            .send(
+                "invite_project_member",
+                api::invite_project_member(&self.http_client, request),
+            )
+            .await?
+            .invitee;
+
+        let public_key = invitee.public_key.as_deref().ok_or_else(|| {
+            crate::error::not_found("the user has no public key until they complete signup")
+        })?;
+        self.share_project_key(
+            workspace_id,
+            &invitee.id,
+            &PublicKey::from_base64(public_key)?,
+            project_key,
+            private_key,
+        )
+        .await?;
+
+        Ok(invitee)
+    }
+
+    /// Adds the user with the given id to a project and shares the project key with them
+    ///
+    /// Infisical adds project members by email, so the user is looked up among the members of the
+    /// project's organization first. Otherwise this is the same as [`Client::add_project_member`].
+    pub async fn add_project_member_by_user_id(
+        &self,
+        workspace_id: &str,
+        user_id: &str,
+        project_key: &ProjectKey,
+        private_key: &UserPrivateKey,
+    ) -> Result<api::models::ProjectInvitee> {
+        let workspace = self.get_project(workspace_id).await?;
+        let email = self
+            .get_organization_memberships(&workspace.organization)
+            .await?
+            .into_iter()
+            .find_map(|membership| membership.user.filter(|user| user.id == user_id))
+            .map(|user| user.email)
+            .ok_or_else(|| {
+                crate::error::not_found(format!(
+                    "no member of the organization with user id {}",
+                    user_id
+                ))
+            })?;
+
+        self.add_project_member(workspace_id, &email, project_key, private_key)
+            .await
+    }
+
     pub async fn get_encrypted_project_key(
         &self,
         workspace_id: &str,
@@ -181,73 +704,67 @@ impl Client {
             workspace_id: workspace_id.to_string(),
         };
 
-        api::get_project_key(&self.http_client, request)
-            .await
-            .map_err(crate::error::reqwest)
+        self.send(
+            "get_project_key",
+            api::get_project_key(&self.http_client, request),
+        )
+        .await
     }
 
     pub async fn get_decrypted_project_key(
         &self,
         workspace_id: &str,
-        private_key: &str,
-    ) -> Result<String> {
+        private_key: &UserPrivateKey,
+    ) -> Result<ProjectKey> {
         let response = self.get_encrypted_project_key(workspace_id).await?;
 
-        let mut encrypted_project_key = vec![0; 16];
-        encrypted_project_key.extend(utils::base64::decode(&response.encrypted_key));
-        let project_nonce = utils::base64::decode(&response.nonce);
-        let public_key = utils::base64::decode(&response.sender.public_key);
-        let private_key = utils::base64::decode(&private_key);
-
-        let project_nonce: [u8; 24] = project_nonce[..24]
-            .try_into()
-            .map_err(crate::error::decrypt)?;
-        let public_key: [u8; 32] = public_key[..32].try_into().map_err(crate::error::decrypt)?;
-        let private_key: [u8; 32] = private_key[..32]
-            .try_into()
-            .map_err(crate::error::decrypt)?;
-
-        let project_nonce = crypto::Nonce(project_nonce);
-        let public_key = crypto::PublicKey(public_key);
-        let private_key = crypto::SecretKey(private_key);
-
-        let mut project_key = Vec::with_capacity(encrypted_project_key.len());
-        for _ in 0..encrypted_project_key.len() {
-            project_key.push(0)
-        }
-        crypto::box_open(
-            &mut project_key,
-            &encrypted_project_key,
-            &project_nonce,
-            &public_key,
-            &private_key,
-        )?;
-        project_key.drain(..32);
-        Ok(String::from_utf8(project_key).map_err(crate::error::utf8)?)
+        self.record_decryption(
+            PublicKey::from_base64(&response.sender.public_key)
+                .and_then(|sender_public_key| {
+                    utils::nacl::decrypt(
+                        &response.encrypted_key,
+                        &response.nonce,
+                        &sender_public_key,
+                        private_key,
+                    )
+                })
+                .and_then(|project_key| String::from_utf8(project_key).map_err(crate::error::utf8))
+                .and_then(|project_key| {
+                    ProjectKey::from_hex(SecretString::from(project_key).expose())
+                }),
+        )
     }
 
+    /// Gets the logs of a project that match `query`
+    ///
+    /// The logs Infisical returns are checked against `query` with
+    /// [`LogQuery::matches`](api::models::LogQuery::matches), so a page may hold fewer logs than
+    /// the limit of the query even when more logs follow.
     pub async fn get_project_logs(
         &self,
         workspace_id: &str,
-        user_id: &str,
-        offset: &str,
-        limit: &str,
-        sort_by: &str,
-        action_names: &str,
+        query: &api::models::LogQuery,
     ) -> Result<Vec<api::models::ProjectLog>> {
-        let request = api::models::GetProjectLogsRequest {
-            base_url: self.api_base.clone(),
-            workspace_id: workspace_id.to_string(),
-            user_id: user_id.to_string(),
-            offset: offset.to_string(),
-            limit: limit.to_string(),
-            sort_by: sort_by.to_string(),
-            action_names: action_names.to_string(),
-        };
+        let mut logs = self.fetch_project_logs(workspace_id, query).await?;
+        logs.retain(|log| query.matches(log));
 
-        let response = api::get_project_logs(&self.http_client, request)
-            .await
-            .map_err(crate::error::reqwest)?;
+        Ok(logs)
+    }
+
+    /// Gets a page of the logs of a project as Infisical returns it
+    async fn fetch_project_logs(
+        &self,
+        workspace_id: &str,
+        query: &api::models::LogQuery,
+    ) -> Result<Vec<api::models::ProjectLog>> {
+        let request = query.to_request(&self.api_base, workspace_id)?;
+
+        let response = self
+            .send(
+                "get_project_logs",
+                api::get_project_logs(&self.http_client, request),
+            )
+            .await?;
 
         Ok(response.logs)
     }
@@ -255,27 +772,157 @@ impl Client {
     pub async fn get_project_snapshots(
         &self,
         workspace_id: &str,
-        offset: &str,
-        limit: &str,
+        offset: u32,
+        limit: u32,
     ) -> Result<Vec<api::models::SecretSnapshot>> {
         let request = api::models::GetProjectSnapshotsRequest {
             base_url: self.api_base.clone(),
             workspace_id: workspace_id.to_string(),
-            offset: offset.to_string(),
-            limit: limit.to_string(),
+            offset,
+            limit,
         };
 
-        let response = api::get_project_snapshots(&self.http_client, request)
-            .await
-            .map_err(crate::error::reqwest)?;
+        let response = self
+            .send(
+                "get_project_snapshots",
+                api::get_project_snapshots(&self.http_client, request),
+            )
+            .await?;
 
         Ok(response.secret_snapshots)
     }
 
+    pub async fn get_project_secret_versions(
+        &self,
+        secret_id: &str,
+        offset: u32,
+        limit: u32,
+    ) -> Result<Vec<api::models::SecretVersion>> {
+        let request = api::models::GetProjectSecretVersionsRequest {
+            base_url: self.api_base.clone(),
+            secret_id: secret_id.to_string(),
+            offset,
+            limit,
+        };
+
+        let response = self
+            .send(
+                "get_project_secret_versions",
+                api::get_project_secret_versions(&self.http_client, request),
+            )
+            .await?;
+
+        Ok(response.secret_versions)
+    }
+
+    /// Streams every log of a project that matches `query`, requesting `page_size` logs at a time
+    ///
+    /// The offset and limit of `query` are replaced by those of each page.
+    pub fn logs_stream<'a>(
+        &'a self,
+        workspace_id: &'a str,
+        query: api::models::LogQuery,
+        page_size: PageSize,
+    ) -> impl Stream<Item = Result<api::models::ProjectLog>> + 'a {
+        let filter = query.clone();
+
+        // Pages are filtered only after paging, since a page that was shortened by the filter
+        // would otherwise be taken for the last one
+        pagination::paginate(page_size, move |offset, limit| {
+            let query = query.clone().offset(offset).limit(limit);
+            async move { self.fetch_project_logs(workspace_id, &query).await }
+        })
+        .try_filter(move |log| futures::future::ready(filter.matches(log)))
+    }
+
+    /// Gets the logs of a project that match `query` as decrypted change events
+    ///
+    /// The secret versions the logs refer to are looked up among the versions of the secrets the
+    /// logs name, stopping as soon as all of them are found. Versions that can't be found, e.g.
+    /// because their secret has since been deleted, are listed in
+    /// [`ChangeEvents::unresolved`](crate::audit::ChangeEvents::unresolved) and versions that fail
+    /// to decrypt in [`ChangeEvents::failures`](crate::audit::ChangeEvents::failures) instead.
+    pub async fn get_change_events(
+        &self,
+        workspace_id: &str,
+        query: &api::models::LogQuery,
+        project_key: &ProjectKey,
+        redaction: &Redaction,
+    ) -> Result<ChangeEvents> {
+        let logs = self.get_project_logs(workspace_id, query).await?;
+        let versions = self
+            .find_secret_versions(audit::secret_versions(&logs))
+            .await?;
+        let events = audit::change_events(&logs, &versions, project_key, redaction);
+
+        if let Some(metrics) = &self.metrics {
+            for failure in &events.failures {
+                metrics.record_decryption_failure(failure.error.kind().as_str());
+            }
+        }
+
+        Ok(events)
+    }
+
+    /// Finds the versions with the given ids among the versions of their secrets
+    async fn find_secret_versions(
+        &self,
+        secret_versions: Vec<(&str, &str)>,
+    ) -> Result<HashMap<String, api::models::SecretVersion>> {
+        let mut wanted: BTreeMap<&str, HashSet<&str>> = BTreeMap::new();
+        for (secret_id, version_id) in secret_versions {
+            wanted.entry(secret_id).or_default().insert(version_id);
+        }
+
+        let mut found = HashMap::new();
+        for (secret_id, mut version_ids) in wanted {
+            let mut versions = pin!(self.secret_versions_stream(secret_id, PageSize::DEFAULT));
+
+            while !version_ids.is_empty() {
+                let version = match versions.try_next().await {
+                    Ok(Some(version)) => version,
+                    Ok(None) => break,
+                    // The secret was deleted, which leaves its versions unresolved
+                    Err(e) if e.status() == Some(reqwest::StatusCode::NOT_FOUND) => break,
+                    Err(e) => return Err(e),
+                };
+                if version_ids.remove(version.id.as_str()) {
+                    found.insert(version.id.clone(), version);
+                }
+            }
+        }
+
+        Ok(found)
+    }
+
+    /// Streams every snapshot of a project, requesting `page_size` snapshots at a time
+    pub fn snapshots_stream<'a>(
+        &'a self,
+        workspace_id: &'a str,
+        page_size: PageSize,
+    ) -> impl Stream<Item = Result<api::models::SecretSnapshot>> + 'a {
+        pagination::paginate(page_size, move |offset, limit| async move {
+            self.get_project_snapshots(workspace_id, offset, limit)
+                .await
+        })
+    }
+
+    /// Streams every version of a secret, requesting `page_size` versions at a time
+    pub fn secret_versions_stream<'a>(
+        &'a self,
+        secret_id: &'a str,
+        page_size: PageSize,
+    ) -> impl Stream<Item = Result<api::models::SecretVersion>> + 'a {
+        pagination::paginate(page_size, move |offset, limit| async move {
+            self.get_project_secret_versions(secret_id, offset, limit)
+                .await
+        })
+    }
+
     pub async fn roll_back_to_snapshot(
         &self,
         workspace_id: &str,
-        version: u8,
+        version: u32,
     ) -> Result<Vec<api::models::EncryptedSecret>> {
         let request = api::models::RollbackProjectToSnapshotRequest {
             base_url: self.api_base.clone(),
@@ -283,9 +930,12 @@ impl Client {
             version,
         };
 
-        let response = api::roll_back_to_snapshot(&self.http_client, request)
-            .await
-            .map_err(crate::error::reqwest)?;
+        let response = self
+            .send(
+                "roll_back_to_snapshot",
+                api::roll_back_to_snapshot(&self.http_client, request),
+            )
+            .await?;
 
         Ok(response.secrets)
     }
@@ -303,9 +953,50 @@ impl Client {
             secrets,
         };
 
-        let response = api::create_project_secrets(&self.http_client, request)
-            .await
-            .map_err(crate::error::reqwest)?;
+        let response = self
+            .send(
+                "create_project_secrets",
+                api::create_project_secrets(&self.http_client, request),
+            )
+            .await?;
+
+        Ok(response.secrets)
+    }
+
+    pub async fn update_project_secrets(
+        &self,
+        secrets: Vec<api::models::SecretToUpdate>,
+    ) -> Result<Vec<api::models::EncryptedSecret>> {
+        let request = api::models::UpdateSecretsRequest {
+            base_url: self.api_base.clone(),
+            secrets,
+        };
+
+        let response = self
+            .send(
+                "update_project_secrets",
+                api::update_project_secrets(&self.http_client, request),
+            )
+            .await?;
+
+        Ok(response.secrets)
+    }
+
+    pub async fn delete_project_secrets(
+        &self,
+        secret_ids: Vec<String>,
+    ) -> Result<Vec<api::models::EncryptedSecret>> {
+        let request = api::models::DeleteProjectSecretsRequest {
+            base_url: self.api_base.clone(),
+            secret_ids,
+        };
+
+        let response = self
+            .send(
+                "delete_project_secrets",
+                api::delete_project_secrets(&self.http_client, request),
+            )
+            .await?;
 
         Ok(response.secrets)
     }
@@ -322,9 +1013,12 @@ impl Client {
             content: String::from(""),
         };
 
-        let response = api::get_project_secrets(&self.http_client, request)
-            .await
-            .map_err(crate::error::reqwest)?;
+        let response = self
+            .send(
+                "get_project_secrets",
+                api::get_project_secrets(&self.http_client, request),
+            )
+            .await?;
 
         Ok(response.secrets)
     }
@@ -333,27 +1027,583 @@ impl Client {
         &self,
         workspace_id: &str,
         environment: &str,
-        private_key: &str,
+        project_key: &ProjectKey,
     ) -> Result<Vec<api::models::DecryptedSecret>> {
         let encrypted_secrets: Vec<api::models::EncryptedSecret> = self
             .get_encrypted_project_secrets(workspace_id, environment)
             .await?;
 
-        encrypted_secrets
+        self.record_decryption(
+            encrypted_secrets
+                .iter()
+                .map(|enc_secret| api::models::EncryptedSecret::decrypt(enc_secret, project_key))
+                .collect(),
+        )
+    }
+
+    /// Gets and decrypts the secrets of a project environment, reporting the secrets that fail to
+    /// decrypt instead of failing as a whole
+    ///
+    /// See [`EncryptedSecret::decrypt_batch`](api::models::EncryptedSecret::decrypt_batch).
+    pub async fn get_decrypted_project_secrets_batch(
+        &self,
+        workspace_id: &str,
+        environment: &str,
+        project_key: &ProjectKey,
+    ) -> Result<api::models::DecryptedSecrets> {
+        let encrypted_secrets = self
+            .get_encrypted_project_secrets(workspace_id, environment)
+            .await?;
+        let decrypted =
+            api::models::EncryptedSecret::decrypt_batch(&encrypted_secrets, project_key);
+
+        if let Some(metrics) = &self.metrics {
+            for failure in &decrypted.failures {
+                metrics.record_decryption_failure(failure.error.kind().as_str());
+            }
+        }
+
+        Ok(decrypted)
+    }
+
+    /// Stores a value that is too large for a single secret as an envelope of chunk secrets
+    ///
+    /// The value is split into chunks whose stored values are at most `chunk_size` bytes, see
+    /// [`envelope::DEFAULT_CHUNK_SIZE`]. Each chunk and the manifest describing them are encrypted
+    /// with `project_key` and created in a single request. Read the value back with
+    /// [`Client::get_envelope_secret`].
+    pub async fn create_envelope_secret(
+        &self,
+        workspace_id: &str,
+        environment: &str,
+        key: &str,
+        value: &[u8],
+        chunk_size: usize,
+        project_key: &ProjectKey,
+    ) -> Result<Vec<api::models::EncryptedSecret>> {
+        let envelope = envelope::seal(key, value, chunk_size)?;
+        let chunk_count = envelope.chunks.len();
+        let encrypt = |text: &str| project_key.encrypt(text);
+
+        let mut secrets = vec![api::models::SecretToCreate {
+            secret_type: "shared".to_string(),
+            key: encrypt(key)?.into(),
+            value: encrypt(&envelope.manifest.to_value())?.into(),
+            comment: encrypt(&format!("Envelope of {} chunks", chunk_count))?.into(),
+        }];
+        for (index, (chunk_key, chunk_value)) in envelope.chunks.iter().enumerate() {
+            secrets.push(api::models::SecretToCreate {
+                secret_type: "shared".to_string(),
+                key: encrypt(chunk_key)?.into(),
+                value: encrypt(chunk_value.expose())?.into(),
+                comment: encrypt(&format!(
+                    "Chunk {} of {} of {}",
+                    index + 1,
+                    chunk_count,
+                    key
+                ))?
+                .into(),
+            });
+        }
+
+        self.create_project_secrets(workspace_id, environment, secrets)
+            .await
+    }
+
+    /// Reads a value stored with [`Client::create_envelope_secret`], reassembling its chunks and
+    /// verifying it against its manifest
+    ///
+    /// Only the keys of the other secrets of the environment are decrypted, so secrets that fail
+    /// to decrypt don't make the envelope unreadable unless they are part of it.
+    pub async fn get_envelope_secret(
+        &self,
+        workspace_id: &str,
+        environment: &str,
+        key: &str,
+        project_key: &ProjectKey,
+    ) -> Result<SecretBytes> {
+        let secrets = self
+            .get_encrypted_project_secrets(workspace_id, environment)
+            .await?;
+        let keys: Vec<Option<String>> = secrets
+            .iter()
+            .map(|secret| secret.key.decrypt(project_key).ok())
+            .collect();
+
+        let manifest_secret = secrets
             .iter()
-            .map(|enc_secret| api::models::EncryptedSecret::decrypt(enc_secret, private_key))
-            .collect()
+            .zip(&keys)
+            .find(|(_, secret_key)| secret_key.as_deref() == Some(key))
+            .map(|(secret, _)| secret)
+            .ok_or_else(|| crate::error::not_found(format!("no secret named {}", key)))?;
+        let manifest_secret = self.record_decryption(api::models::EncryptedSecret::decrypt(
+            manifest_secret,
+            project_key,
+        ))?;
+        let manifest = envelope::Manifest::from_value(manifest_secret.value.expose())
+            .unwrap_or_else(|| {
+                Err(crate::error::invalid_input(
+                    "envelope",
+                    format!("{} is not an envelope", key),
+                ))
+            })?;
+
+        let chunks: Vec<_> = secrets
+            .into_iter()
+            .zip(keys)
+            .filter(|(_, secret_key)| {
+                secret_key
+                    .as_ref()
+                    .is_some_and(|secret_key| manifest.chunks.contains(secret_key))
+            })
+            .map(|(secret, _)| secret)
+            .collect();
+        let chunks = api::models::EncryptedSecret::decrypt_batch(&chunks, project_key);
+        if let Some(failure) = chunks.failures.into_iter().next() {
+            return self.record_decryption(Err(failure.error));
+        }
+
+        self.record_decryption(envelope::open(&manifest, &chunks.secrets))
     }
 
-    pub async fn get_user_decrypted_private_key(&self, infisical_secret: &str) -> Result<String> {
+    pub async fn get_user_decrypted_private_key(
+        &self,
+        infisical_secret: &str,
+    ) -> Result<UserPrivateKey> {
         let user = self.get_user().await?;
-        utils::aes256gcm::decrypt(
-            &user.encrypted_private_key,
-            &user.iv,
-            &user.tag,
-            &infisical_secret,
+        self.record_decryption(user.decrypt_private_key(infisical_secret))
+    }
+
+    /// Rotates the key of a project, for example after a member left
+    ///
+    /// Every secret of every environment is decrypted with `old_key` and re-encrypted with the new
+    /// key held by `checkpoint`, one environment at a time. The new key is then shared with every
+    /// member of the project, including the current user, using their `private_key`. Remove any
+    /// departing members before rotating so they don't receive the new key. Members whose public
+    /// key is missing or malformed are listed in the report instead of failing the rotation.
+    ///
+    /// `checkpoint` is updated as each environment and member completes. If the rotation fails,
+    /// call this again with the same checkpoint to resume it. Secrets that already decrypt with the
+    /// new key are left as they are, so resuming is safe even if the checkpoint wasn't saved after
+    /// the secrets of an environment were uploaded. With `dry_run`, everything is
+    /// fetched, decrypted and re-encrypted but nothing is uploaded and the checkpoint is left
+    /// untouched. `on_progress` is called after each step.
+    ///
+    /// Only the secrets visible to the current user are re-encrypted, so the personal secrets of
+    /// other members can no longer be decrypted after a rotation.
+    pub async fn rotate_project_key<F>(
+        &self,
+        workspace_id: &str,
+        old_key: &ProjectKey,
+        private_key: &UserPrivateKey,
+        checkpoint: &mut RotationCheckpoint,
+        dry_run: bool,
+        mut on_progress: F,
+    ) -> Result<RotationReport>
+    where
+        F: FnMut(RotationProgress),
+    {
+        let mut report = RotationReport {
+            dry_run,
+            ..RotationReport::default()
+        };
+        let workspace = self.get_project(workspace_id).await?;
+
+        for environment in &workspace.environments {
+            if checkpoint.is_environment_completed(&environment.slug) {
+                on_progress(RotationProgress::EnvironmentSkipped {
+                    environment: environment.slug.clone(),
+                });
+                continue;
+            }
+
+            let mut secrets = Vec::new();
+            for secret in self
+                .get_encrypted_project_secrets(workspace_id, &environment.slug)
+                .await?
+            {
+                // A previous attempt may have uploaded the secrets without its checkpoint being
+                // saved, in which case they no longer decrypt with the old key
+                if api::models::EncryptedSecret::decrypt(&secret, checkpoint.new_key()).is_ok() {
+                    report.already_reencrypted_secrets += 1;
+                    continue;
+                }
+
+                secrets.push(self.record_decryption(reencrypt_secret(
+                    &secret,
+                    old_key,
+                    checkpoint.new_key(),
+                ))?);
+            }
+            let count = secrets.len();
+
+            if !dry_run {
+                if !secrets.is_empty() {
+                    self.update_project_secrets(secrets).await?;
+                }
+                checkpoint.complete_environment(&environment.slug);
+            }
+
+            report.reencrypted_secrets += count;
+            on_progress(RotationProgress::EnvironmentReencrypted {
+                environment: environment.slug.clone(),
+                secrets: count,
+            });
+        }
+
+        for membership in self.get_project_memberships(workspace_id).await? {
+            let user_id = membership.user.id;
+
+            if checkpoint.is_shared_with(&user_id) {
+                on_progress(RotationProgress::MemberSkipped { user_id });
+                continue;
+            }
+
+            let Some(public_key) = membership.user.public_key else {
+                report.members_without_public_key.push(user_id.clone());
+                on_progress(RotationProgress::MemberWithoutPublicKey { user_id });
+                continue;
+            };
+            let Ok(public_key) = PublicKey::from_base64(&public_key) else {
+                report.members_with_invalid_public_key.push(user_id.clone());
+                on_progress(RotationProgress::MemberWithInvalidPublicKey { user_id });
+                continue;
+            };
+
+            if !dry_run {
+                self.share_project_key(
+                    workspace_id,
+                    &user_id,
+                    &public_key,
+                    checkpoint.new_key(),
+                    private_key,
+                )
+                .await?;
+                checkpoint.complete_share(&user_id);
+            }
+
+            report.shared_with.push(user_id.clone());
+            on_progress(RotationProgress::KeyShared { user_id });
+        }
+
+        Ok(report)
+    }
+
+    /// Computes the changes needed to make the projects of an organization match `spec`
+    ///
+    /// `private_key` is only used to decrypt the keys of existing secrets, for projects whose
+    /// spec lists secrets. See the [`reconcile`](crate::reconcile) module.
+    pub async fn plan(&self, spec: &Spec, private_key: &UserPrivateKey) -> Result<Plan> {
+        spec.validate()?;
+
+        let user = self.get_user().await?;
+        let workspaces = self.get_organization_projects(&spec.organization).await?;
+        let mut plan = Plan::default();
+
+        for project in &spec.projects {
+            let (id, changes) = match workspaces.iter().find(|w| w.name == project.name) {
+                Some(workspace) => {
+                    let live = self
+                        .get_live_project(workspace, project, None, private_key)
+                        .await?;
+                    let changes = reconcile::diff_project(project, Some(&live), &user.email);
+
+                    (Some(workspace.id.clone()), changes)
+                }
+                None => (None, reconcile::diff_project(project, None, &user.email)),
+            };
+
+            if !changes.is_empty() {
+                plan.projects.push(ProjectPlan {
+                    name: project.name.clone(),
+                    id,
+                    changes,
+                });
+            }
+        }
+
+        Ok(plan)
+    }
+
+    /// Carries out a plan computed by [`Client::plan`] for the same `spec`
+    ///
+    /// Projects are created first. Infisical gives new projects default environments, so the
+    /// changes to a new project are computed again right after it is created. `private_key` is
+    /// used to decrypt the project keys, to share them with new members and to encrypt the keys
+    /// of new secrets.
+    pub async fn apply(
+        &self,
+        spec: &Spec,
+        plan: &Plan,
+        private_key: &UserPrivateKey,
+    ) -> Result<()> {
+        let user = self.get_user().await?;
+
+        for project_plan in &plan.projects {
+            let project = spec
+                .projects
+                .iter()
+                .find(|p| p.name == project_plan.name)
+                .ok_or_else(|| {
+                    crate::error::invalid_input(
+                        "plan",
+                        format!("project {} is not in the spec", project_plan.name),
+                    )
+                })?;
+
+            match &project_plan.id {
+                Some(workspace_id) => {
+                    self.apply_changes(workspace_id, &project_plan.changes, None, private_key)
+                        .await?
+                }
+                None => {
+                    let (workspace, project_key) = self
+                        .create_project(&spec.organization, &project.name, private_key)
+                        .await?;
+                    let live = self
+                        .get_live_project(&workspace, project, Some(&project_key), private_key)
+                        .await?;
+                    let changes = reconcile::diff_project(project, Some(&live), &user.email);
+
+                    self.apply_changes(&workspace.id, &changes, Some(project_key), private_key)
+                        .await?
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Fetches the parts of a project that its spec manages
+    async fn get_live_project(
+        &self,
+        workspace: &api::models::Workspace,
+        project: &ProjectSpec,
+        project_key: Option<&ProjectKey>,
+        private_key: &UserPrivateKey,
+    ) -> Result<LiveProject> {
+        let memberships = match project.members {
+            Some(_) => self.get_project_memberships(&workspace.id).await?,
+            None => Vec::new(),
+        };
+
+        let mut secrets = BTreeMap::new();
+        if let Some(secret_specs) = &project.secrets {
+            let project_key = match project_key {
+                Some(project_key) => project_key.clone(),
+                None => {
+                    self.get_decrypted_project_key(&workspace.id, private_key)
+                        .await?
+                }
+            };
+
+            for environment in secret_specs.keys() {
+                if !workspace
+                    .environments
+                    .iter()
+                    .any(|e| &e.slug == environment)
+                {
+                    continue;
+                }
+
+                let keys = self
+                    .get_decrypted_project_secrets(&workspace.id, environment, &project_key)
+                    .await?
+                    .into_iter()
+                    .filter(|secret| secret.type_name == "shared")
+                    .map(|secret| (secret.id, secret.key))
+                    .collect();
+                secrets.insert(environment.clone(), keys);
+            }
+        }
+
+        Ok(LiveProject {
+            environments: workspace.environments.clone(),
+            memberships,
+            secrets,
+        })
+    }
+
+    async fn apply_changes(
+        &self,
+        workspace_id: &str,
+        changes: &[Change],
+        mut project_key: Option<ProjectKey>,
+        private_key: &UserPrivateKey,
+    ) -> Result<()> {
+        for change in changes {
+            match change {
+                Change::CreateProject => {}
+                Change::CreateEnvironment { name, slug } => {
+                    self.create_environment(workspace_id, name, slug).await?;
+                }
+                Change::RenameEnvironment { slug, name } => {
+                    self.rename_environment(workspace_id, slug, name, slug)
+                        .await?;
+                }
+                Change::DeleteEnvironment { slug } => {
+                    self.delete_environment(workspace_id, slug).await?;
+                }
+                Change::ReorderEnvironments { slugs } => {
+                    let slugs: Vec<&str> = slugs.iter().map(String::as_str).collect();
+                    self.reorder_environments(workspace_id, &slugs).await?;
+                }
+                Change::CreateSecret { environment, key } => {
+                    let project_key = self
+                        .cached_project_key(&mut project_key, workspace_id, private_key)
+                        .await?;
+                    let secret = api::models::SecretToCreate {
+                        secret_type: "shared".to_string(),
+                        key: project_key.encrypt(key)?.into(),
+                        value: project_key.encrypt("")?.into(),
+                        comment: project_key.encrypt("")?.into(),
+                    };
+                    self.create_project_secrets(workspace_id, environment, vec![secret])
+                        .await?;
+                }
+                Change::DeleteSecret { id, .. } => {
+                    self.delete_project_secrets(vec![id.clone()]).await?;
+                }
+                Change::AddMember { email, role } => {
+                    let project_key = self
+                        .cached_project_key(&mut project_key, workspace_id, private_key)
+                        .await?;
+                    let invitee = self
+                        .add_project_member(workspace_id, email, project_key, private_key)
+                        .await?;
+
+                    if *role != api::models::Role::Member {
+                        let membership = self
+                            .get_project_memberships(workspace_id)
+                            .await?
+                            .into_iter()
+                            .find(|m| m.user.id == invitee.id)
+                            .ok_or_else(|| {
+                                crate::error::not_found(format!("no membership for {}", email))
+                            })?;
+                        self.update_project_membership(workspace_id, &membership.id, role.clone())
+                            .await?;
+                    }
+                }
+                Change::UpdateMemberRole {
+                    role,
+                    membership_id,
+                    ..
+                } => {
+                    self.update_project_membership(workspace_id, membership_id, role.clone())
+                        .await?;
+                }
+                Change::RemoveMember { membership_id, .. } => {
+                    self.delete_project_membership(workspace_id, membership_id)
+                        .await?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns the project key in `cache`, decrypting it first if it hasn't been yet
+    async fn cached_project_key<'a>(
+        &self,
+        cache: &'a mut Option<ProjectKey>,
+        workspace_id: &str,
+        private_key: &UserPrivateKey,
+    ) -> Result<&'a ProjectKey> {
+        if let Some(metrics) = &self.metrics {
+            metrics.record_cache(cache.is_some());
+        }
+
+        let project_key = match cache.take() {
+            Some(project_key) => project_key,
+            None => {
+                self.get_decrypted_project_key(workspace_id, private_key)
+                    .await?
+            }
+        };
+
+        Ok(cache.insert(project_key))
+    }
+
+    /// Encrypts a project key for a user's public key and uploads it
+    async fn share_project_key(
+        &self,
+        workspace_id: &str,
+        user_id: &str,
+        public_key: &PublicKey,
+        project_key: &ProjectKey,
+        private_key: &UserPrivateKey,
+    ) -> Result<()> {
+        let encryption =
+            utils::nacl::encrypt(project_key.expose().as_bytes(), public_key, private_key);
+
+        let request = api::models::UploadProjectKeyRequest {
+            base_url: self.api_base.clone(),
+            workspace_id: workspace_id.to_string(),
+            key: api::models::ProjectKeyUpload {
+                user_id: user_id.to_string(),
+                encrypted_key: encryption.ciphertext,
+                nonce: encryption.nonce,
+            },
+        };
+
+        self.send(
+            "upload_project_key",
+            api::upload_project_key(&self.http_client, request),
         )
+        .await?;
+
+        Ok(())
     }
+
+    /// Awaits a request to the Infisical API, reporting it to the metrics recorder if one is set
+    async fn send<T, F>(&self, endpoint: &'static str, request: F) -> Result<T>
+    where
+        F: Future<Output = Result<T>>,
+    {
+        let start = Instant::now();
+        let result = request.await;
+
+        if let Some(metrics) = &self.metrics {
+            metrics.record_request(
+                endpoint,
+                start.elapsed(),
+                result.as_ref().err().map(|e| e.kind().as_str()),
+            );
+        }
+
+        result
+    }
+
+    /// Reports a failed decryption to the metrics recorder if one is set
+    fn record_decryption<T>(&self, result: Result<T>) -> Result<T> {
+        if let (Some(metrics), Err(e)) = (&self.metrics, &result) {
+            metrics.record_decryption_failure(e.kind().as_str());
+        }
+
+        result
+    }
+}
+
+/// Decrypts a secret with `old_key` and encrypts it again with `new_key`
+fn reencrypt_secret(
+    secret: &api::models::EncryptedSecret,
+    old_key: &ProjectKey,
+    new_key: &ProjectKey,
+) -> Result<api::models::SecretToUpdate> {
+    let secret = api::models::EncryptedSecret::decrypt(secret, old_key)?;
+
+    Ok(api::models::SecretToUpdate {
+        id: secret.id.clone(),
+        key: new_key.encrypt(&secret.key)?.into(),
+        value: new_key.encrypt(secret.value.expose())?.into(),
+        comment: secret
+            .comment
+            .as_deref()
+            .map(|comment| new_key.encrypt(comment))
+            .transpose()?
+            .map(Into::into),
+    })
 }
 
 /// `ClientBuilder` can be used to create a `Client` with a custom API endpoint and/or [`Reqwest
@@ -363,6 +1613,7 @@ impl Client {
 pub struct ClientBuilder {
     api_base: String,
     reqwest_client_builder: Option<reqwest::ClientBuilder>,
+    metrics: Option<Arc<dyn MetricsRecorder>>,
 }
 
 impl Default for ClientBuilder {
@@ -376,6 +1627,7 @@ impl ClientBuilder {
         ClientBuilder {
             api_base: String::from("https://app.infisical.com/api"),
             reqwest_client_builder: None,
+            metrics: None,
         }
     }
 
@@ -400,6 +1652,7 @@ impl ClientBuilder {
                         .build()
                         .map_err(crate::error::builder)?,
                     api_base: self.api_base.clone(),
+                    metrics: self.metrics,
                 })
             }
             None => unreachable!("There will always be a reqwest_client_builder at this point"),
@@ -416,4 +1669,13 @@ impl ClientBuilder {
         self.reqwest_client_builder = Some(value);
         self
     }
+
+    /// Sets a [`MetricsRecorder`] that will be notified of every request and decryption failure
+    pub fn metrics<M>(mut self, recorder: M) -> ClientBuilder
+    where
+        M: MetricsRecorder + 'static,
+    {
+        self.metrics = Some(Arc::new(recorder));
+        self
+    }
 }
@@ -0,0 +1,307 @@
+//! Envelopes for secret values that are too large to be stored as a single secret
+//!
+//! An envelope splits a value into chunks that are each stored as their own secret, named after
+//! the envelope's key with a `__CHUNK_<n>` suffix. The secret with the envelope's key holds a
+//! [`Manifest`] listing the chunks along with the size and SHA-256 digest of the whole value, so
+//! that the value can be reassembled and verified when it is read back.
+//!
+//! Each chunk is encrypted with the project key like any other secret. See
+//! [`Client::create_envelope_secret`] and [`Client::get_envelope_secret`].
+//!
+//! [`Client::create_envelope_secret`]: crate::Client::create_envelope_secret
+//! [`Client::get_envelope_secret`]: crate::Client::get_envelope_secret
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::api::models::{wrap_binary_value, DecryptedSecret, BINARY_VALUE_PREFIX};
+use crate::error::{self, Result};
+use crate::secret::{SecretBytes, SecretString};
+use crate::utils::hex;
+
+/// The prefix that marks a secret value as an envelope manifest
+pub const MANIFEST_PREFIX: &str = "envelope:";
+
+/// A chunk size that stays well below the size Infisical accepts for a secret value
+///
+/// Chunk sizes are the size of the stored value of each chunk secret, after it is wrapped with
+/// [`wrap_binary_value`], rather than the number of bytes of the original value it holds.
+pub const DEFAULT_CHUNK_SIZE: usize = 32 * 1024;
+
+const MANIFEST_VERSION: u8 = 1;
+
+/// Describes the chunks an envelope's value was split into
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct Manifest {
+    /// The version of the manifest format
+    pub version: u8,
+    /// The size of the whole value in bytes
+    pub size: usize,
+    /// The hexadecimal SHA-256 digest of the whole value
+    pub sha256: String,
+    /// The keys of the secrets holding the chunks, in order
+    pub chunks: Vec<String>,
+}
+
+impl Manifest {
+    /// Parses a manifest from a secret value, returning `None` if the value isn't a manifest
+    pub fn from_value(value: &str) -> Option<Result<Manifest>> {
+        let manifest = value.strip_prefix(MANIFEST_PREFIX)?;
+
+        Some(
+            serde_json::from_str::<Manifest>(manifest)
+                .map_err(|e| error::decode("manifest", e))
+                .and_then(|manifest| {
+                    if manifest.version == MANIFEST_VERSION {
+                        Ok(manifest)
+                    } else {
+                        Err(error::decode(
+                            "manifest",
+                            format!("unsupported version {}", manifest.version),
+                        ))
+                    }
+                }),
+        )
+    }
+
+    /// Returns the manifest as a secret value
+    pub fn to_value(&self) -> String {
+        // Serializing a struct of strings and numbers can't fail
+        let manifest = serde_json::to_string(self).unwrap_or_default();
+
+        format!("{}{}", MANIFEST_PREFIX, manifest)
+    }
+}
+
+/// A value split into chunks, ready to be encrypted and stored as secrets
+#[derive(Debug)]
+pub struct Envelope {
+    /// The manifest, to be stored under the envelope's key
+    pub manifest: Manifest,
+    /// The key and value of each chunk secret, in order
+    pub chunks: Vec<(String, SecretString)>,
+}
+
+/// Splits `value` into chunks for the secret `key`, each stored as a value of at most
+/// `chunk_size` bytes
+///
+/// Chunks are wrapped in base64, so each holds about three quarters of `chunk_size` bytes of
+/// `value`. `chunk_size` must leave room for the [`BINARY_VALUE_PREFIX`] and at least one base64
+/// block.
+pub fn seal(key: &str, value: &[u8], chunk_size: usize) -> Result<Envelope> {
+    // Every 3 bytes of the value take up a block of 4 base64 characters
+    let bytes_per_chunk = chunk_size.saturating_sub(BINARY_VALUE_PREFIX.len()) / 4 * 3;
+    if bytes_per_chunk == 0 {
+        return Err(error::invalid_input(
+            "chunk size",
+            format!("must be at least {}", BINARY_VALUE_PREFIX.len() + 4),
+        ));
+    }
+
+    let chunks: Vec<_> = value
+        .chunks(bytes_per_chunk)
+        .enumerate()
+        .map(|(index, chunk)| {
+            (
+                format!("{}__CHUNK_{}", key, index),
+                wrap_binary_value(chunk),
+            )
+        })
+        .collect();
+
+    Ok(Envelope {
+        manifest: Manifest {
+            version: MANIFEST_VERSION,
+            size: value.len(),
+            sha256: hex::encode(&Sha256::digest(value)),
+            chunks: chunks.iter().map(|(key, _)| key.clone()).collect(),
+        },
+        chunks,
+    })
+}
+
+/// Reassembles the value described by `manifest` from the decrypted secrets of its environment
+///
+/// Fails if the chunk indices in the manifest skip or repeat an index, if a chunk is missing or
+/// stored more than once, or if the reassembled value doesn't match the size and digest recorded
+/// in the manifest.
+pub fn open(manifest: &Manifest, secrets: &[DecryptedSecret]) -> Result<SecretBytes> {
+    let mut value = Vec::with_capacity(manifest.size);
+
+    for (index, chunk_key) in manifest.chunks.iter().enumerate() {
+        if !chunk_key.ends_with(&format!("__CHUNK_{}", index)) {
+            return Err(error::decode(
+                "envelope",
+                format!("chunk {} is listed at index {}", chunk_key, index),
+            ));
+        }
+
+        let mut chunks = secrets.iter().filter(|secret| &secret.key == chunk_key);
+        let chunk = chunks
+            .next()
+            .ok_or_else(|| error::decode("envelope", format!("missing chunk {}", chunk_key)))?;
+        if chunks.next().is_some() {
+            return Err(error::decode(
+                "envelope",
+                format!("duplicate chunk {}", chunk_key),
+            ));
+        }
+
+        value.extend_from_slice(chunk.value_bytes()?.expose());
+    }
+
+    // Wrap the value first so it is zeroized even if it fails verification
+    let value = SecretBytes::new(value);
+
+    if value.expose().len() != manifest.size
+        || hex::encode(&Sha256::digest(value.expose())) != manifest.sha256
+    {
+        return Err(error::decode(
+            "envelope",
+            "the reassembled value doesn't match the manifest",
+        ));
+    }
+
+    Ok(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keys::ProjectKey;
+
+    fn decrypted_secrets(envelope: &Envelope) -> Vec<DecryptedSecret> {
+        let project_key = ProjectKey::generate();
+
+        envelope
+            .chunks
+            .iter()
+            .map(|(key, value)| {
+                let key = project_key.encrypt(key).unwrap();
+                let value = project_key.encrypt(value.expose()).unwrap();
+                let secret = serde_json::from_value(serde_json::json!({
+                    "_id": "secret-id",
+                    "version": 1,
+                    "workspace": "workspace-id",
+                    "type": "shared",
+                    "secretKeyCiphertext": key.text,
+                    "secretKeyIV": key.nonce,
+                    "secretKeyTag": key.tag,
+                    "secretValueCiphertext": value.text,
+                    "secretValueIV": value.nonce,
+                    "secretValueTag": value.tag,
+                    "createdAt": "2023-04-01T00:00:00.000Z",
+                    "updatedAt": "2023-04-01T00:00:00.000Z",
+                }))
+                .unwrap();
+
+                crate::api::models::EncryptedSecret::decrypt(&secret, &project_key).unwrap()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn open_inverts_seal() {
+        let value: Vec<u8> = (0..=255).cycle().take(1000).collect();
+        // Leaves room for 300 bytes of the value per chunk
+        let envelope = seal("KEYSTORE", &value, BINARY_VALUE_PREFIX.len() + 400).unwrap();
+
+        assert_eq!(
+            envelope.manifest.chunks,
+            [
+                "KEYSTORE__CHUNK_0",
+                "KEYSTORE__CHUNK_1",
+                "KEYSTORE__CHUNK_2",
+                "KEYSTORE__CHUNK_3"
+            ]
+        );
+
+        let secrets = decrypted_secrets(&envelope);
+        let opened = open(&envelope.manifest, &secrets).unwrap();
+
+        assert_eq!(opened.expose(), value);
+    }
+
+    #[test]
+    fn chunks_fit_in_chunk_size() {
+        let chunk_size = BINARY_VALUE_PREFIX.len() + 10;
+        let envelope = seal("KEYSTORE", &[0xff; 100], chunk_size).unwrap();
+
+        for (_, chunk) in &envelope.chunks {
+            assert!(chunk.expose().len() <= chunk_size);
+        }
+        assert_eq!(envelope.chunks.len(), 17);
+    }
+
+    #[test]
+    fn open_rejects_missing_chunk() {
+        let envelope = seal("KEYSTORE", &[1; 10], BINARY_VALUE_PREFIX.len() + 4).unwrap();
+        let mut secrets = decrypted_secrets(&envelope);
+        secrets.remove(1);
+
+        let err = open(&envelope.manifest, &secrets).unwrap_err();
+
+        assert!(err.is_decode());
+        assert!(err.to_string().contains("KEYSTORE__CHUNK_1"));
+    }
+
+    #[test]
+    fn open_rejects_duplicate_chunk() {
+        let envelope = seal("KEYSTORE", &[1; 10], BINARY_VALUE_PREFIX.len() + 4).unwrap();
+        let mut secrets = decrypted_secrets(&envelope);
+        secrets.extend(decrypted_secrets(&envelope).into_iter().skip(1).take(1));
+
+        let err = open(&envelope.manifest, &secrets).unwrap_err();
+
+        assert!(err.is_decode());
+        assert!(err
+            .to_string()
+            .contains("duplicate chunk KEYSTORE__CHUNK_1"));
+    }
+
+    #[test]
+    fn open_rejects_skipped_or_repeated_chunk_indices() {
+        let envelope = seal("KEYSTORE", &[1; 10], BINARY_VALUE_PREFIX.len() + 4).unwrap();
+        let secrets = decrypted_secrets(&envelope);
+
+        let mut manifest = envelope.manifest.clone();
+        manifest.chunks[1] = manifest.chunks[0].clone();
+        assert!(open(&manifest, &secrets).unwrap_err().is_decode());
+
+        let mut manifest = envelope.manifest.clone();
+        manifest.chunks.remove(1);
+        assert!(open(&manifest, &secrets).unwrap_err().is_decode());
+    }
+
+    #[test]
+    fn open_rejects_tampered_chunk() {
+        let envelope = seal("KEYSTORE", b"0123456789", BINARY_VALUE_PREFIX.len() + 4).unwrap();
+        let mut secrets = decrypted_secrets(&envelope);
+        secrets[0].value = wrap_binary_value(b"abc");
+
+        assert!(open(&envelope.manifest, &secrets).unwrap_err().is_decode());
+    }
+
+    #[test]
+    fn manifest_round_trips_through_value() {
+        let envelope = seal("KEYSTORE", b"value", DEFAULT_CHUNK_SIZE).unwrap();
+        let value = envelope.manifest.to_value();
+
+        assert!(value.starts_with(MANIFEST_PREFIX));
+        assert_eq!(
+            Manifest::from_value(&value).unwrap().unwrap(),
+            envelope.manifest
+        );
+        assert!(Manifest::from_value("not a manifest").is_none());
+    }
+
+    #[test]
+    fn seal_rejects_chunk_size_without_room_for_data() {
+        assert!(seal("KEYSTORE", b"value", 0)
+            .unwrap_err()
+            .is_invalid_input());
+        assert!(seal("KEYSTORE", b"value", BINARY_VALUE_PREFIX.len() + 3)
+            .unwrap_err()
+            .is_invalid_input());
+    }
+}
@@ -1,6 +1,9 @@
 use onionsalt::crypto::NaClError;
 use std::error::Error as StdError;
 use std::fmt;
+use std::string::FromUtf8Error;
+
+use zeroize::Zeroize;
 
 use crate::api::models::ErrorResponse;
 
@@ -31,6 +34,103 @@ impl Error {
             }),
         }
     }
+
+    /// Returns the kind of this error
+    pub fn kind(&self) -> &Kind {
+        &self.inner.kind
+    }
+
+    /// Returns true if the error is an error response returned by the Infisical API
+    pub fn is_api(&self) -> bool {
+        matches!(self.inner.kind, Kind::API)
+    }
+
+    /// Returns true if the error occurred while decrypting a key or secret
+    pub fn is_decrypt(&self) -> bool {
+        matches!(self.inner.kind, Kind::Decrypt | Kind::NaCl)
+    }
+
+    /// Returns true if a key, nonce, tag or ciphertext was malformed, e.g. not valid base64 or of
+    /// the wrong length
+    pub fn is_decode(&self) -> bool {
+        matches!(self.inner.kind, Kind::Decode)
+    }
+
+    /// Returns true if an argument was rejected, e.g. an invalid environment slug or page size
+    pub fn is_invalid_input(&self) -> bool {
+        matches!(self.inner.kind, Kind::InvalidInput)
+    }
+
+    /// Returns true if something the operation relies on, such as a secret or a pending
+    /// invitation, does not exist
+    pub fn is_not_found(&self) -> bool {
+        matches!(self.inner.kind, Kind::NotFound)
+    }
+
+    /// Returns true if the error originated from reqwest
+    pub fn is_reqwest(&self) -> bool {
+        matches!(self.inner.kind, Kind::Reqwest)
+    }
+
+    /// Returns the error response returned by the Infisical API, if this is an API error
+    pub fn api_error(&self) -> Option<&ErrorResponse> {
+        self.inner
+            .source
+            .as_ref()
+            .and_then(|e| e.downcast_ref::<ErrorResponse>())
+    }
+
+    /// Returns the HTTP status code associated with the error, if there is one
+    ///
+    /// For API errors this is the status code reported by Infisical in the error response.
+    pub fn status(&self) -> Option<reqwest::StatusCode> {
+        if let Some(api_error) = self.api_error() {
+            return u16::try_from(api_error.status_code)
+                .ok()
+                .and_then(|code| reqwest::StatusCode::from_u16(code).ok());
+        }
+
+        if let Some(response) = self.unexpected_response() {
+            return Some(response.status);
+        }
+
+        self.inner
+            .source
+            .as_ref()
+            .and_then(|e| e.downcast_ref::<reqwest::Error>())
+            .and_then(reqwest::Error::status)
+    }
+
+    /// Returns the raw body of the response, if the Infisical API returned a response that could
+    /// not be understood
+    pub fn response_body(&self) -> Option<&str> {
+        self.unexpected_response()
+            .map(|response| response.body.as_str())
+    }
+
+    fn unexpected_response(&self) -> Option<&UnexpectedResponse> {
+        self.inner
+            .source
+            .as_ref()
+            .and_then(|e| e.downcast_ref::<UnexpectedResponse>())
+    }
+
+    /// Returns true if the request that caused this error may succeed if it is retried
+    ///
+    /// This is the case for timeouts, connection failures, rate limiting and server errors.
+    pub fn is_retryable(&self) -> bool {
+        if let Some(status) = self.status() {
+            return status == reqwest::StatusCode::TOO_MANY_REQUESTS
+                || status == reqwest::StatusCode::REQUEST_TIMEOUT
+                || status.is_server_error();
+        }
+
+        self.inner
+            .source
+            .as_ref()
+            .and_then(|e| e.downcast_ref::<reqwest::Error>())
+            .is_some_and(|e| e.is_timeout() || e.is_connect())
+    }
 }
 
 impl fmt::Debug for Error {
@@ -52,15 +152,21 @@ impl fmt::Display for Error {
         match self.inner.kind {
             Kind::Encrypt => f.write_str("Encryption error")?,
             Kind::Decrypt => f.write_str("Decryption error")?,
+            Kind::Decode => f.write_str("Decode error")?,
+            Kind::InvalidInput => f.write_str("Invalid input")?,
+            Kind::NotFound => f.write_str("Not found")?,
             Kind::Reqwest => f.write_str("Reqwest error")?,
             Kind::UTF8 => f.write_str("UTF8 error")?,
             Kind::NaCl => f.write_str("NaCl error")?,
             Kind::Builder => f.write_str("Builder error")?,
             Kind::API => f.write_str("Infisical API error")?,
+            Kind::UnexpectedResponse => {
+                f.write_str("Unexpected response from the Infisical API")?
+            }
         };
 
         if let Some(e) = &self.inner.source {
-            write!(f, ": {}", e);
+            write!(f, ": {}", e)?;
         }
 
         Ok(())
@@ -79,23 +185,100 @@ impl StdError for ErrorResponse {
     }
 }
 
-impl fmt::Display for ErrorResponse {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        f.write_str("Infisical API error");
+/// A value that could not be decoded or was rejected, along with the name of the field it came
+/// from
+#[derive(Debug)]
+struct InvalidField {
+    field: &'static str,
+    source: BoxError,
+}
 
-        Ok(())
+impl StdError for InvalidField {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        Some(&*self.source)
     }
 }
 
+impl fmt::Display for InvalidField {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "invalid {}: {}", self.field, self.source)
+    }
+}
+
+/// A response from the Infisical API that was neither the expected response nor an
+/// [`ErrorResponse`]
 #[derive(Debug)]
-pub(crate) enum Kind {
+struct UnexpectedResponse {
+    status: reqwest::StatusCode,
+    body: String,
+    source: serde_json::Error,
+}
+
+impl StdError for UnexpectedResponse {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        Some(&self.source)
+    }
+}
+
+impl fmt::Display for UnexpectedResponse {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "status {}: {}", self.status, self.source)
+    }
+}
+
+impl fmt::Display for ErrorResponse {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} (status code {})", self.message, self.status_code)
+    }
+}
+
+/// The kinds of errors that may occur
+///
+/// More kinds may be added in the future, so matches on `Kind` should include a wildcard arm.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Kind {
+    /// Encrypting a value failed
     Encrypt,
+    /// Decrypting a value failed, usually because the wrong key was used
     Decrypt,
+    /// A key, nonce, tag or ciphertext was malformed
+    Decode,
+    /// An argument was rejected before any request was made
+    InvalidInput,
+    /// Something the operation relies on, such as a secret or a pending invitation, does not exist
+    NotFound,
+    /// The request could not be sent or its response could not be read
     Reqwest,
+    /// A decrypted value was not valid UTF-8
     UTF8,
+    /// A NaCl box operation failed while decrypting a project key
     NaCl,
+    /// The `Client` could not be built
     Builder,
+    /// The Infisical API returned an error response
     API,
+    /// The Infisical API returned a response that was not in the expected format
+    UnexpectedResponse,
+}
+
+impl Kind {
+    /// Returns a stable, lowercase name for the kind, suitable for use as a metric label
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Kind::Encrypt => "encrypt",
+            Kind::Decrypt => "decrypt",
+            Kind::Decode => "decode",
+            Kind::InvalidInput => "invalid_input",
+            Kind::NotFound => "not_found",
+            Kind::Reqwest => "reqwest",
+            Kind::UTF8 => "utf8",
+            Kind::NaCl => "nacl",
+            Kind::Builder => "builder",
+            Kind::API => "api",
+            Kind::UnexpectedResponse => "unexpected_response",
+        }
+    }
 }
 
 impl From<aes_gcm::Error> for Error {
@@ -110,14 +293,6 @@ impl From<NaClError> for Error {
     }
 }
 
-// This just swallows any detail that InvalidLength would provide
-// TODO: Find a better way to handle
-impl From<aes_gcm::aes::cipher::InvalidLength> for Error {
-    fn from(err: aes_gcm::aes::cipher::InvalidLength) -> Error {
-        Error::new(Kind::Decrypt, None::<Error>)
-    }
-}
-
 impl From<reqwest::Error> for Error {
     fn from(err: reqwest::Error) -> Error {
         reqwest(err)
@@ -134,22 +309,62 @@ pub(crate) fn encrypt<E: Into<BoxError>>(e: E) -> Error {
     Error::new(Kind::Encrypt, Some(e))
 }
 
-pub(crate) fn decrypt<E: Into<BoxError>>(e: E) -> Error {
-    Error::new(Kind::Decrypt, Some(e))
+pub(crate) fn decode<E: Into<BoxError>>(field: &'static str, e: E) -> Error {
+    Error::new(
+        Kind::Decode,
+        Some(InvalidField {
+            field,
+            source: e.into(),
+        }),
+    )
+}
+
+pub(crate) fn invalid_input<E: Into<BoxError>>(field: &'static str, e: E) -> Error {
+    Error::new(
+        Kind::InvalidInput,
+        Some(InvalidField {
+            field,
+            source: e.into(),
+        }),
+    )
+}
+
+pub(crate) fn not_found<E: Into<BoxError>>(e: E) -> Error {
+    Error::new(Kind::NotFound, Some(e))
 }
 
 pub(crate) fn reqwest<E: Into<BoxError>>(e: E) -> Error {
     Error::new(Kind::Reqwest, Some(e))
 }
 
-pub(crate) fn utf8<E: Into<BoxError>>(e: E) -> Error {
-    Error::new(Kind::UTF8, Some(e))
+/// Keeps only where the invalid UTF-8 starts, since the decrypted bytes the error holds must not
+/// end up in the error's output
+pub(crate) fn utf8(e: FromUtf8Error) -> Error {
+    let utf8_error = e.utf8_error();
+    e.into_bytes().zeroize();
+
+    Error::new(Kind::UTF8, Some(utf8_error))
 }
 
 pub(crate) fn api<E: Into<BoxError>>(e: E) -> Error {
     Error::new(Kind::API, Some(e))
 }
 
+pub(crate) fn unexpected_response(
+    status: reqwest::StatusCode,
+    body: String,
+    source: serde_json::Error,
+) -> Error {
+    Error::new(
+        Kind::UnexpectedResponse,
+        Some(UnexpectedResponse {
+            status,
+            body,
+            source,
+        }),
+    )
+}
+
 pub(crate) fn nacl(e: NaClError) -> Error {
     match e {
         NaClError::IOError(io_error) => Error::new(Kind::NaCl, Some(io_error)),
@@ -162,3 +377,57 @@ pub(crate) fn nacl(e: NaClError) -> Error {
 pub(crate) fn builder<E: Into<BoxError>>(e: E) -> Error {
     Error::new(Kind::Builder, Some(e))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn error_response(status_code: i16) -> ErrorResponse {
+        ErrorResponse {
+            type_name: String::from("not_found"),
+            message: String::from("Failed to find secret"),
+            context: Default::default(),
+            level: 30,
+            level_name: String::from("INFO"),
+            status_code,
+            datetime_iso: String::from("2023-04-01T00:00:00.000Z"),
+            application: String::from("infisical"),
+            extra: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn api_error_is_inspectable() {
+        let err = Error::from(error_response(404));
+
+        assert!(err.is_api());
+        assert!(!err.is_decrypt());
+        assert_eq!(err.kind(), &Kind::API);
+        assert_eq!(err.status(), Some(reqwest::StatusCode::NOT_FOUND));
+        assert_eq!(
+            err.api_error().map(|e| e.message.as_str()),
+            Some("Failed to find secret")
+        );
+        assert!(!err.is_retryable());
+        assert_eq!(
+            err.to_string(),
+            "Infisical API error: Failed to find secret (status code 404)"
+        );
+    }
+
+    #[test]
+    fn server_errors_are_retryable() {
+        assert!(Error::from(error_response(503)).is_retryable());
+        assert!(Error::from(error_response(429)).is_retryable());
+    }
+
+    #[test]
+    fn decrypt_error_has_no_status() {
+        let err = Error::from(aes_gcm::Error);
+
+        assert!(err.is_decrypt());
+        assert!(err.api_error().is_none());
+        assert!(err.status().is_none());
+        assert!(!err.is_retryable());
+    }
+}
@@ -0,0 +1,354 @@
+//! Distinct types for the keys used by Infisical's end-to-end encryption
+//!
+//! Infisical uses three kinds of keys that are all passed around as strings by the API:
+//!
+//! - a [`UserPrivateKey`], the X25519 private key of a user, which is itself stored encrypted
+//!   with the user's password
+//! - a [`PublicKey`], the X25519 public key of a user
+//! - a [`ProjectKey`], the symmetric key used to encrypt the secrets of a project, which is shared
+//!   with each project member by encrypting it for their public key
+//!
+//! New users get their keys from [`UserKeyPair::generate`].
+//!
+//! Giving each its own type makes it a compile error to pass one where another is expected.
+
+use std::fmt;
+
+use aes_gcm::aead::{rand_core::RngCore, OsRng};
+use onionsalt::crypto;
+use zeroize::Zeroize;
+
+use crate::error::{self, Result};
+use crate::secret::SecretString;
+use crate::utils::{aes256gcm, base64, hex};
+
+/// The X25519 private key of an Infisical user
+///
+/// The key is zeroized on drop and redacted when formatted with `Debug`.
+#[derive(Clone)]
+pub struct UserPrivateKey([u8; 32]);
+
+impl UserPrivateKey {
+    /// Constructs a `UserPrivateKey` from a base64 encoded key, as stored by Infisical
+    pub fn from_base64(value: &str) -> Result<UserPrivateKey> {
+        base64::decode_array::<32>("private key", value).map(UserPrivateKey)
+    }
+
+    /// Constructs a `UserPrivateKey` from its raw bytes
+    pub fn from_bytes(bytes: [u8; 32]) -> UserPrivateKey {
+        UserPrivateKey(bytes)
+    }
+
+    /// Returns the raw bytes of the key
+    pub fn as_bytes(&self) -> &[u8; 32] {
+        &self.0
+    }
+
+    /// Returns the key base64 encoded, as stored by Infisical
+    pub fn to_base64(&self) -> SecretString {
+        SecretString::new(base64::encode(&self.0))
+    }
+}
+
+impl Drop for UserPrivateKey {
+    fn drop(&mut self) {
+        self.0.zeroize();
+    }
+}
+
+impl fmt::Debug for UserPrivateKey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("UserPrivateKey([REDACTED])")
+    }
+}
+
+/// The X25519 public key of an Infisical user
+#[derive(Clone, PartialEq, Eq)]
+pub struct PublicKey([u8; 32]);
+
+impl PublicKey {
+    /// Constructs a `PublicKey` from a base64 encoded key, as returned by the Infisical API
+    pub fn from_base64(value: &str) -> Result<PublicKey> {
+        base64::decode_array::<32>("public key", value).map(PublicKey)
+    }
+
+    /// Constructs a `PublicKey` from its raw bytes
+    pub fn from_bytes(bytes: [u8; 32]) -> PublicKey {
+        PublicKey(bytes)
+    }
+
+    /// Returns the raw bytes of the key
+    pub fn as_bytes(&self) -> &[u8; 32] {
+        &self.0
+    }
+
+    /// Returns the key base64 encoded, as expected by the Infisical API
+    pub fn to_base64(&self) -> String {
+        base64::encode(&self.0)
+    }
+}
+
+impl fmt::Debug for PublicKey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("PublicKey").field(&self.to_base64()).finish()
+    }
+}
+
+/// A newly generated X25519 key pair for an Infisical user
+#[derive(Clone, Debug)]
+pub struct UserKeyPair {
+    /// The public key, which is shared with other users
+    pub public_key: PublicKey,
+    /// The private key, which is only ever uploaded encrypted
+    pub private_key: UserPrivateKey,
+}
+
+impl UserKeyPair {
+    /// Generates a random key pair
+    pub fn generate() -> UserKeyPair {
+        let mut key_pair = crypto::box_keypair();
+        let user_key_pair = UserKeyPair {
+            public_key: PublicKey::from_bytes(key_pair.public.0),
+            private_key: UserPrivateKey::from_bytes(key_pair.secret.0),
+        };
+        key_pair.secret.0.zeroize();
+
+        user_key_pair
+    }
+
+    /// Encrypts the private key with the user's password, producing the fields Infisical expects
+    /// when a user signs up or uploads their keys
+    ///
+    /// The private key is encrypted the same way as for encryption version 1 users, so it can be
+    /// decrypted with [`User::decrypt_private_key`](crate::api::models::User::decrypt_private_key).
+    pub fn encrypt(&self, password: &str) -> Result<EncryptedUserKeyPair> {
+        let encryption = aes256gcm::encrypt(self.private_key.to_base64().expose(), password)?;
+
+        let mut salt = [0; 16];
+        OsRng.fill_bytes(&mut salt);
+
+        Ok(EncryptedUserKeyPair {
+            public_key: self.public_key.to_base64(),
+            encrypted_private_key: encryption.text,
+            iv: encryption.nonce,
+            tag: encryption.tag,
+            salt: hex::encode(&salt),
+        })
+    }
+}
+
+/// A user key pair with the private key encrypted, ready to be uploaded to Infisical
+#[derive(Clone, Debug)]
+pub struct EncryptedUserKeyPair {
+    /// The base64 encoded public key
+    pub public_key: String,
+    /// The base64 encoded encrypted private key
+    pub encrypted_private_key: String,
+    /// The base64 encoded nonce used to encrypt the private key
+    pub iv: String,
+    /// The base64 encoded authentication tag of the encrypted private key
+    pub tag: String,
+    /// A random hexadecimal salt for the user's password
+    pub salt: String,
+}
+
+/// The symmetric key used to encrypt and decrypt the secrets of an Infisical project
+///
+/// Infisical project keys are 16 random bytes encoded as 32 hexadecimal characters. The
+/// hexadecimal string itself is used as the AES-256-GCM key. The key is zeroized on drop and
+/// redacted when formatted with `Debug`.
+#[derive(Clone)]
+pub struct ProjectKey(SecretString);
+
+impl ProjectKey {
+    /// Constructs a `ProjectKey` from its 32 character hexadecimal representation
+    pub fn from_hex(value: &str) -> Result<ProjectKey> {
+        if value.len() != 32 {
+            return Err(error::decode(
+                "project key",
+                format!("expected 32 hexadecimal characters, got {}", value.len()),
+            ));
+        }
+
+        if !value.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(error::decode(
+                "project key",
+                "expected only hexadecimal characters",
+            ));
+        }
+
+        Ok(ProjectKey(SecretString::from(value)))
+    }
+
+    /// Generates a random project key
+    pub fn generate() -> ProjectKey {
+        let mut bytes = [0; 16];
+        OsRng.fill_bytes(&mut bytes);
+        let project_key = ProjectKey::from_bytes(&bytes);
+        bytes.zeroize();
+
+        project_key
+    }
+
+    /// Constructs a `ProjectKey` from the 16 random bytes it is generated from
+    pub fn from_bytes(bytes: &[u8; 16]) -> ProjectKey {
+        ProjectKey(SecretString::new(hex::encode(bytes)))
+    }
+
+    /// Returns the hexadecimal representation of the key, which is used as the encryption key
+    pub fn expose(&self) -> &str {
+        self.0.expose()
+    }
+
+    /// Returns the AES-256-GCM key made of the bytes of the hexadecimal representation
+    pub(crate) fn aes_key(&self) -> Result<aes256gcm::AesKey> {
+        aes256gcm::AesKey::from_slice(self.expose().as_bytes())
+    }
+
+    /// Encrypts `text` with the key, as Infisical does for the keys, values and comments of
+    /// secrets
+    pub fn encrypt(&self, text: &str) -> Result<aes256gcm::Encryption> {
+        aes256gcm::encrypt_bytes(text.as_bytes(), &self.aes_key()?)
+    }
+
+    /// Decrypts UTF-8 text encrypted with the key from its base64 encoded ciphertext, nonce and
+    /// authentication tag
+    pub fn decrypt(&self, text: &str, nonce: &str, tag: &str) -> Result<String> {
+        let plaintext = aes256gcm::decrypt_bytes(text, nonce, tag, &self.aes_key()?)?;
+
+        String::from_utf8(plaintext).map_err(error::utf8)
+    }
+}
+
+impl fmt::Debug for ProjectKey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("ProjectKey([REDACTED])")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn private_key_requires_32_bytes() {
+        assert!(UserPrivateKey::from_base64(&base64::encode(&[7; 32])).is_ok());
+
+        let err = UserPrivateKey::from_base64(&base64::encode(&[7; 31])).unwrap_err();
+        assert!(err.is_decode());
+    }
+
+    #[test]
+    fn public_key_round_trips_base64() {
+        let encoded = base64::encode(&[42; 32]);
+
+        assert_eq!(
+            PublicKey::from_base64(&encoded).unwrap().to_base64(),
+            encoded
+        );
+    }
+
+    #[test]
+    fn project_key_requires_32_hex_characters() {
+        assert!(ProjectKey::from_hex("0123456789abcdef0123456789ABCDEF").is_ok());
+        assert!(ProjectKey::from_hex("0123456789abcdef").is_err());
+        assert!(ProjectKey::from_hex("0123456789abcdef0123456789abcdeg").is_err());
+    }
+
+    #[test]
+    fn project_key_from_bytes_is_hex() {
+        let key = ProjectKey::from_bytes(&[0xab; 16]);
+
+        assert_eq!(key.expose(), "ab".repeat(16));
+    }
+
+    #[test]
+    fn project_key_encryption_matches_legacy_strings() {
+        let key = ProjectKey::from_bytes(&[0xab; 16]);
+        let encrypted = key.encrypt("value").unwrap();
+
+        assert_eq!(
+            aes256gcm::decrypt(
+                &encrypted.text,
+                &encrypted.nonce,
+                &encrypted.tag,
+                key.expose()
+            )
+            .unwrap(),
+            "value"
+        );
+        assert_eq!(
+            key.decrypt(&encrypted.text, &encrypted.nonce, &encrypted.tag)
+                .unwrap(),
+            "value"
+        );
+        assert!(ProjectKey::generate()
+            .decrypt(&encrypted.text, &encrypted.nonce, &encrypted.tag)
+            .unwrap_err()
+            .is_decrypt());
+    }
+
+    #[test]
+    fn invalid_utf8_error_does_not_reveal_plaintext() {
+        let key = ProjectKey::generate();
+        let plaintext = b"hunter2\xff";
+        let encrypted = aes256gcm::encrypt_bytes(plaintext, &key.aes_key().unwrap()).unwrap();
+
+        let err = key
+            .decrypt(&encrypted.text, &encrypted.nonce, &encrypted.tag)
+            .unwrap_err();
+
+        let debug = format!("{:?}", err);
+        assert_eq!(err.kind(), &error::Kind::UTF8);
+        assert!(!debug.contains("hunter2"));
+        assert!(!debug.contains(&format!("{:?}", &plaintext[..])));
+        assert!(!debug.contains("104, 117, 110"));
+    }
+
+    #[test]
+    fn generated_key_pair_decrypts_with_password() {
+        let key_pair = UserKeyPair::generate();
+        let encrypted = key_pair.encrypt("hunter2").unwrap();
+
+        let private_key = aes256gcm::decrypt(
+            &encrypted.encrypted_private_key,
+            &encrypted.iv,
+            &encrypted.tag,
+            "hunter2",
+        )
+        .unwrap();
+
+        assert_eq!(private_key, key_pair.private_key.to_base64().expose());
+        assert_eq!(
+            PublicKey::from_base64(&encrypted.public_key).unwrap(),
+            key_pair.public_key
+        );
+        assert_eq!(encrypted.salt.len(), 32);
+    }
+
+    #[test]
+    fn generated_keys_are_unique() {
+        let first = UserKeyPair::generate();
+        let second = UserKeyPair::generate();
+
+        assert_ne!(first.public_key, second.public_key);
+        assert_ne!(first.private_key.as_bytes(), second.private_key.as_bytes());
+    }
+
+    #[test]
+    fn generated_project_key_is_valid() {
+        let key = ProjectKey::generate();
+
+        assert!(ProjectKey::from_hex(key.expose()).is_ok());
+        assert_ne!(key.expose(), ProjectKey::generate().expose());
+    }
+
+    #[test]
+    fn keys_are_redacted() {
+        let private_key = UserPrivateKey::from_bytes([1; 32]);
+        let project_key = ProjectKey::from_bytes(&[1; 16]);
+
+        assert_eq!(format!("{:?}", private_key), "UserPrivateKey([REDACTED])");
+        assert_eq!(format!("{:?}", project_key), "ProjectKey([REDACTED])");
+    }
+}
@@ -54,8 +54,14 @@
 //! # use infisical_api::Error;
 //! # async fn run() -> Result<(), Error> {
 //! let client = infisical_api::Client::new("Your API key here")?;
+//! let project_key = infisical_api::ProjectKey::from_hex("Your project key")?;
 //! let secrets = client
-//!     .get_decrypted_project_secrets("Your Infisical workspace ID", "Environment here", "Your project key").await?;
+//!     .get_decrypted_project_secrets("Your Infisical workspace ID", "Environment here", &project_key).await?;
+//!
+//! for secret in secrets {
+//!     // Decrypted values are redacted when printed and must be exposed explicitly
+//!     println!("{}: {}", secret.key, secret.value.expose());
+//! }
 //!
 //! # Ok(())
 //! # }
@@ -77,14 +83,27 @@
 //! ```
 
 pub mod api;
+pub mod audit;
 pub mod client;
+pub mod envelope;
 pub mod error;
+pub mod keys;
+pub mod metrics;
+pub mod pagination;
+pub mod rbac;
+pub mod reconcile;
+pub mod rotation;
+pub mod secret;
 pub mod utils;
 
 #[doc(inline)]
 pub use self::client::{Client, ClientBuilder};
 #[doc(inline)]
 pub use self::error::Error;
+#[doc(inline)]
+pub use self::keys::{ProjectKey, PublicKey, UserKeyPair, UserPrivateKey};
+#[doc(inline)]
+pub use self::secret::{SecretBytes, SecretString};
 pub use reqwest;
 
 #[cfg(test)]
@@ -0,0 +1,196 @@
+//! Hooks for recording metrics about the work a [`Client`][crate::Client] performs.
+//!
+//! A [`MetricsRecorder`] can be registered on the [`ClientBuilder`][crate::ClientBuilder]. The
+//! client will then report every API request it makes along with its latency and the kind of
+//! error that occurred, if any, every lookup of a cached project key and every failed attempt to
+//! decrypt a key or secret.
+//!
+//! Adapters for the [`metrics`](https://docs.rs/metrics) crate and for
+//! [OpenTelemetry](https://docs.rs/opentelemetry) are available behind the `metrics` and
+//! `opentelemetry` features respectively.
+//!
+//! ```rust
+//! # use infisical_api::Error;
+//! # use std::time::Duration;
+//! use infisical_api::metrics::MetricsRecorder;
+//!
+//! struct StdoutRecorder;
+//!
+//! impl MetricsRecorder for StdoutRecorder {
+//!     fn record_request(&self, endpoint: &'static str, latency: Duration, error: Option<&'static str>) {
+//!         println!("{} took {:?} (error: {:?})", endpoint, latency, error);
+//!     }
+//!
+//!     fn record_decryption_failure(&self, error: &'static str) {
+//!         println!("decryption failed: {}", error);
+//!     }
+//! }
+//!
+//! # fn run() -> Result<(), Error> {
+//! let client = infisical_api::ClientBuilder::new()
+//!     .metrics(StdoutRecorder)
+//!     .build("Your API key")?;
+//! # Ok(())
+//! # }
+//! ```
+
+use std::time::Duration;
+
+/// A sink for the metrics produced by a [`Client`][crate::Client]
+///
+/// Error kinds are reported using [`Kind::as_str`][crate::error::Kind::as_str], e.g. `"api"`,
+/// `"reqwest"` or `"decrypt"`, so they can be used directly as metric labels.
+pub trait MetricsRecorder: Send + Sync {
+    /// Called once for every request made to the Infisical API
+    ///
+    /// `endpoint` is the name of the [`api`][crate::api] function that was called and `error` is
+    /// the kind of error the request failed with, or `None` if it succeeded.
+    fn record_request(
+        &self,
+        endpoint: &'static str,
+        latency: Duration,
+        error: Option<&'static str>,
+    );
+
+    /// Called whenever the client fails to decrypt a private key, project key or secret
+    fn record_decryption_failure(&self, error: &'static str);
+
+    /// Called whenever the client looks up a project key it caches while applying a plan
+    ///
+    /// `hit` is `false` if the key had to be fetched and decrypted first. Does nothing by default.
+    fn record_cache(&self, hit: bool) {
+        let _ = hit;
+    }
+}
+
+/// A [`MetricsRecorder`] that reports to the global recorder of the [`metrics`](https://docs.rs/metrics) crate
+///
+/// The following metrics are recorded:
+///
+/// - `infisical_api_requests_total` counter, labelled with `endpoint` and `status` (`ok` or `error`)
+/// - `infisical_api_request_errors_total` counter, labelled with `endpoint` and `kind`
+/// - `infisical_api_request_duration_seconds` histogram, labelled with `endpoint`
+/// - `infisical_api_decryption_failures_total` counter, labelled with `kind`
+/// - `infisical_api_cache_lookups_total` counter, labelled with `result` (`hit` or `miss`)
+#[cfg(feature = "metrics")]
+#[derive(Debug, Default, Clone, Copy)]
+pub struct MetricsCrateRecorder;
+
+#[cfg(feature = "metrics")]
+impl MetricsCrateRecorder {
+    /// Constructs a new `MetricsCrateRecorder`
+    pub fn new() -> MetricsCrateRecorder {
+        MetricsCrateRecorder
+    }
+}
+
+#[cfg(feature = "metrics")]
+impl MetricsRecorder for MetricsCrateRecorder {
+    fn record_request(
+        &self,
+        endpoint: &'static str,
+        latency: Duration,
+        error: Option<&'static str>,
+    ) {
+        let status = if error.is_some() { "error" } else { "ok" };
+
+        ::metrics::counter!("infisical_api_requests_total", 1, "endpoint" => endpoint, "status" => status);
+        ::metrics::histogram!("infisical_api_request_duration_seconds", latency.as_secs_f64(), "endpoint" => endpoint);
+
+        if let Some(kind) = error {
+            ::metrics::counter!("infisical_api_request_errors_total", 1, "endpoint" => endpoint, "kind" => kind);
+        }
+    }
+
+    fn record_decryption_failure(&self, error: &'static str) {
+        ::metrics::counter!("infisical_api_decryption_failures_total", 1, "kind" => error);
+    }
+
+    fn record_cache(&self, hit: bool) {
+        let result = if hit { "hit" } else { "miss" };
+
+        ::metrics::counter!("infisical_api_cache_lookups_total", 1, "result" => result);
+    }
+}
+
+/// A [`MetricsRecorder`] that reports to an [OpenTelemetry](https://docs.rs/opentelemetry) [`Meter`][opentelemetry::metrics::Meter]
+///
+/// The instruments use the same names and attributes as [`MetricsCrateRecorder`].
+#[cfg(feature = "opentelemetry")]
+pub struct OpenTelemetryRecorder {
+    requests: opentelemetry::metrics::Counter<u64>,
+    request_errors: opentelemetry::metrics::Counter<u64>,
+    request_duration: opentelemetry::metrics::Histogram<f64>,
+    decryption_failures: opentelemetry::metrics::Counter<u64>,
+    cache_lookups: opentelemetry::metrics::Counter<u64>,
+}
+
+#[cfg(feature = "opentelemetry")]
+impl OpenTelemetryRecorder {
+    /// Constructs a new `OpenTelemetryRecorder` whose instruments are created from `meter`
+    pub fn new(meter: &opentelemetry::metrics::Meter) -> OpenTelemetryRecorder {
+        OpenTelemetryRecorder {
+            requests: meter.u64_counter("infisical_api_requests_total").init(),
+            request_errors: meter
+                .u64_counter("infisical_api_request_errors_total")
+                .init(),
+            request_duration: meter
+                .f64_histogram("infisical_api_request_duration_seconds")
+                .init(),
+            decryption_failures: meter
+                .u64_counter("infisical_api_decryption_failures_total")
+                .init(),
+            cache_lookups: meter
+                .u64_counter("infisical_api_cache_lookups_total")
+                .init(),
+        }
+    }
+}
+
+#[cfg(feature = "opentelemetry")]
+impl MetricsRecorder for OpenTelemetryRecorder {
+    fn record_request(
+        &self,
+        endpoint: &'static str,
+        latency: Duration,
+        error: Option<&'static str>,
+    ) {
+        use opentelemetry::KeyValue;
+
+        let status = if error.is_some() { "error" } else { "ok" };
+
+        self.requests.add(
+            1,
+            &[
+                KeyValue::new("endpoint", endpoint),
+                KeyValue::new("status", status),
+            ],
+        );
+        self.request_duration.record(
+            latency.as_secs_f64(),
+            &[KeyValue::new("endpoint", endpoint)],
+        );
+
+        if let Some(kind) = error {
+            self.request_errors.add(
+                1,
+                &[
+                    KeyValue::new("endpoint", endpoint),
+                    KeyValue::new("kind", kind),
+                ],
+            );
+        }
+    }
+
+    fn record_decryption_failure(&self, error: &'static str) {
+        self.decryption_failures
+            .add(1, &[opentelemetry::KeyValue::new("kind", error)]);
+    }
+
+    fn record_cache(&self, hit: bool) {
+        let result = if hit { "hit" } else { "miss" };
+
+        self.cache_lookups
+            .add(1, &[opentelemetry::KeyValue::new("result", result)]);
+    }
+}
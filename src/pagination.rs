@@ -0,0 +1,114 @@
+//! Paging through list endpoints with streams
+//!
+//! Endpoints that return one page at a time, such as the logs and snapshots of a project, have
+//! `Stream`-returning variants on [`Client`](crate::Client) that request the next page as the
+//! previous one is consumed, and end after the first page that isn't full. A failed request is
+//! yielded as an error and ends the stream.
+
+use std::future::Future;
+
+use futures::stream::{self, Stream, TryStreamExt};
+
+use crate::error::{self, Result};
+
+/// The number of items requested per page, which is never zero
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct PageSize(u32);
+
+impl PageSize {
+    /// The page size used by default
+    pub const DEFAULT: PageSize = PageSize(50);
+
+    /// Constructs a `PageSize`, failing if `size` is zero
+    pub fn new(size: u32) -> Result<PageSize> {
+        if size == 0 {
+            return Err(error::invalid_input("page size", "must not be zero"));
+        }
+
+        Ok(PageSize(size))
+    }
+
+    /// Returns the number of items per page
+    pub fn get(self) -> u32 {
+        self.0
+    }
+}
+
+impl Default for PageSize {
+    fn default() -> PageSize {
+        PageSize::DEFAULT
+    }
+}
+
+/// Streams the items of the pages returned by `fetch_page(offset, limit)`
+pub(crate) fn paginate<'a, T, F, Fut>(
+    page_size: PageSize,
+    fetch_page: F,
+) -> impl Stream<Item = Result<T>> + 'a
+where
+    T: 'a,
+    F: FnMut(u32, u32) -> Fut + 'a,
+    Fut: Future<Output = Result<Vec<T>>> + 'a,
+{
+    let limit = page_size.get();
+
+    stream::try_unfold(
+        (fetch_page, 0, false),
+        move |(mut fetch_page, offset, exhausted)| async move {
+            if exhausted {
+                return Ok::<_, error::Error>(None);
+            }
+
+            let page = fetch_page(offset, limit).await?;
+            let len = page.len() as u32;
+            let items = stream::iter(page.into_iter().map(Ok));
+
+            Ok(Some((items, (fetch_page, offset + len, len < limit))))
+        },
+    )
+    .try_flatten()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::executor::block_on;
+    use futures::StreamExt;
+
+    #[test]
+    fn page_size_rejects_zero() {
+        assert!(PageSize::new(0).unwrap_err().is_invalid_input());
+        assert_eq!(PageSize::new(10).unwrap().get(), 10);
+    }
+
+    #[test]
+    fn paginate_stops_after_partial_page() {
+        let mut requests = Vec::new();
+        let items: Vec<u32> = block_on(
+            paginate(PageSize::new(2).unwrap(), |offset, limit| {
+                requests.push((offset, limit));
+                async move { Ok((offset..5.min(offset + limit)).collect()) }
+            })
+            .try_collect(),
+        )
+        .unwrap();
+
+        assert_eq!(items, [0, 1, 2, 3, 4]);
+        assert_eq!(requests, [(0, 2), (2, 2), (4, 2)]);
+    }
+
+    #[test]
+    fn paginate_stops_after_error() {
+        let mut calls = 0;
+        let results: Vec<Result<u32>> = block_on(
+            paginate(PageSize::new(1).unwrap(), |_, _| {
+                calls += 1;
+                async { Err(error::decode("page", "failed")) }
+            })
+            .collect::<Vec<_>>(),
+        );
+
+        assert_eq!(results.len(), 1);
+        assert_eq!(calls, 1);
+    }
+}
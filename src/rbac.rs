@@ -0,0 +1,351 @@
+//! Local checks of what project members may do with secrets
+//!
+//! [`Rbac`] answers whether a member may perform an action on the secrets of an environment from
+//! their role, the roles returned by [`Client::get_roles`] and their denied permissions, without
+//! making any requests. It is meant to let tooling check an operation before trying it, and
+//! Infisical remains the authority on what is actually allowed.
+//!
+//! [`Client::get_roles`]: crate::Client::get_roles
+
+use serde_json::Value;
+
+use crate::api::models::{
+    Ability, CustomRole, PermissionAction, ProjectMembership, Role, RolePermission,
+};
+
+/// The subject of the permissions that apply to secrets
+pub const SECRETS_SUBJECT: &str = "secrets";
+
+/// Evaluates the permissions of project members against a set of roles
+#[derive(Clone, Debug)]
+pub struct Rbac {
+    roles: Vec<CustomRole>,
+}
+
+impl Rbac {
+    /// Constructs an `Rbac` from the roles of a project, as returned by [`Client::get_roles`]
+    ///
+    /// [`Client::get_roles`]: crate::Client::get_roles
+    pub fn new(roles: Vec<CustomRole>) -> Rbac {
+        Rbac { roles }
+    }
+
+    /// Returns true if `membership` allows `action` on the secrets of `environment_slug`
+    ///
+    /// Denied permissions take precedence over the role. A role is looked up by its id for
+    /// [`Role::Custom`] and by its slug otherwise. Built-in roles that aren't among the roles fall
+    /// back to their documented defaults, and unknown roles are allowed nothing. Only environment
+    /// conditions are evaluated. A rule with any other condition, such as a secret path, or with
+    /// an environment condition that isn't understood applies to its environments if it forbids
+    /// the action but never if it allows it.
+    pub fn can(
+        &self,
+        membership: &ProjectMembership,
+        action: &PermissionAction,
+        environment_slug: &str,
+    ) -> bool {
+        let ability = match action {
+            PermissionAction::Read => Ability::Read,
+            _ => Ability::Write,
+        };
+        if membership.is_denied(&ability, environment_slug) {
+            return false;
+        }
+
+        match self.role_of(membership) {
+            Some(role) => allows(&role.permissions, action, environment_slug),
+            None => match membership.role {
+                Role::Owner | Role::Admin | Role::Member => true,
+                Role::Viewer => action == &PermissionAction::Read,
+                _ => false,
+            },
+        }
+    }
+
+    fn role_of(&self, membership: &ProjectMembership) -> Option<&CustomRole> {
+        match &membership.role {
+            Role::Custom => {
+                let id = membership.custom_role.as_deref()?;
+                self.roles
+                    .iter()
+                    .find(|role| role.id.as_deref() == Some(id))
+            }
+            role => self.roles.iter().find(|r| r.slug == role.as_str()),
+        }
+    }
+}
+
+/// Returns true if a rule allows the action and no rule forbids it
+///
+/// Conditions that can't be evaluated, such as secret paths or globs, never let a rule allow an
+/// action but always let it forbid one, so that a forbidding rule can't be bypassed and an
+/// allowing rule can't grant more than it does in Infisical.
+fn allows(
+    permissions: &[RolePermission],
+    action: &PermissionAction,
+    environment_slug: &str,
+) -> bool {
+    let mut allowed = false;
+
+    for permission in permissions
+        .iter()
+        .filter(|p| p.subject == SECRETS_SUBJECT && &p.action == action)
+    {
+        let applies = applies_to(permission.conditions.as_ref(), environment_slug);
+        if permission.inverted {
+            if applies.unwrap_or(true) {
+                return false;
+            }
+        } else if applies.unwrap_or(false) {
+            allowed = true;
+        }
+    }
+
+    allowed
+}
+
+/// Returns whether the conditions of a rule match the environment, or `None` if they can't be
+/// evaluated because they restrict more than the environment or use an unsupported operator
+fn applies_to(conditions: Option<&Value>, environment_slug: &str) -> Option<bool> {
+    let Some(conditions) = conditions else {
+        return Some(true);
+    };
+    let conditions = conditions.as_object()?;
+
+    if let Some(environment) = conditions.get("environment") {
+        if !environment_matches(environment, environment_slug)? {
+            return Some(false);
+        }
+    }
+
+    if conditions.keys().any(|key| key != "environment") {
+        return None;
+    }
+
+    Some(true)
+}
+
+/// Returns whether an environment condition matches the environment, or `None` if its operator
+/// isn't supported
+fn environment_matches(environment: &Value, environment_slug: &str) -> Option<bool> {
+    match environment {
+        Value::String(slug) => Some(slug == environment_slug),
+        Value::Object(operator) => match (operator.get("$eq"), operator.get("$in")) {
+            (Some(Value::String(slug)), _) => Some(slug == environment_slug),
+            (_, Some(Value::Array(slugs))) => {
+                Some(slugs.iter().any(|slug| slug == environment_slug))
+            }
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::models::DeniedPermission;
+    use serde_json::json;
+
+    fn membership(role: &str, custom_role: Option<&str>) -> ProjectMembership {
+        serde_json::from_value(json!({
+            "_id": "membership-id",
+            "role": role,
+            "customRole": custom_role,
+            "workspace": "workspace-id",
+            "deniedPermissions": [],
+            "user": {
+                "_id": "user-id",
+                "email": "ci@example.com",
+                "firstName": "CI",
+                "lastName": "Bot",
+                "__v": 0,
+                "devices": [],
+                "isMfaEnabled": false,
+                "mfaMethods": [],
+                "createdAt": "2023-04-01T00:00:00.000Z",
+                "updatedAt": "2023-04-01T00:00:00.000Z",
+            },
+            "createdAt": "2023-04-01T00:00:00.000Z",
+            "updatedAt": "2023-04-01T00:00:00.000Z",
+        }))
+        .unwrap()
+    }
+
+    fn deployer() -> CustomRole {
+        CustomRole {
+            id: Some("role-id".to_string()),
+            name: "Deployer".to_string(),
+            slug: "deployer".to_string(),
+            description: None,
+            permissions: vec![
+                RolePermission::allow(PermissionAction::Read, SECRETS_SUBJECT),
+                RolePermission::allow(PermissionAction::Edit, SECRETS_SUBJECT)
+                    .in_environment("staging"),
+            ],
+            workspace: Some("workspace-id".to_string()),
+        }
+    }
+
+    #[test]
+    fn custom_role_permissions_are_scoped_to_environments() {
+        let rbac = Rbac::new(vec![deployer()]);
+        let membership = membership("custom", Some("role-id"));
+
+        assert!(rbac.can(&membership, &PermissionAction::Read, "prod"));
+        assert!(rbac.can(&membership, &PermissionAction::Edit, "staging"));
+        assert!(!rbac.can(&membership, &PermissionAction::Edit, "prod"));
+        assert!(!rbac.can(&membership, &PermissionAction::Delete, "staging"));
+    }
+
+    #[test]
+    fn inverted_permissions_forbid() {
+        let mut role = deployer();
+        role.permissions.push(RolePermission {
+            inverted: true,
+            ..RolePermission::allow(PermissionAction::Read, SECRETS_SUBJECT).in_environment("prod")
+        });
+        let rbac = Rbac::new(vec![role]);
+        let membership = membership("custom", Some("role-id"));
+
+        assert!(!rbac.can(&membership, &PermissionAction::Read, "prod"));
+        assert!(rbac.can(&membership, &PermissionAction::Read, "dev"));
+    }
+
+    #[test]
+    fn denied_permissions_take_precedence() {
+        let rbac = Rbac::new(Vec::new());
+        let mut membership = membership("admin", None);
+        membership.denied_permissions = vec![DeniedPermission::write("prod")];
+
+        assert!(rbac.can(&membership, &PermissionAction::Read, "prod"));
+        assert!(!rbac.can(&membership, &PermissionAction::Create, "prod"));
+        assert!(rbac.can(&membership, &PermissionAction::Create, "dev"));
+    }
+
+    #[test]
+    fn built_in_roles_fall_back_to_defaults() {
+        let rbac = Rbac::new(Vec::new());
+
+        assert!(rbac.can(
+            &membership("member", None),
+            &PermissionAction::Delete,
+            "dev"
+        ));
+        assert!(rbac.can(&membership("viewer", None), &PermissionAction::Read, "dev"));
+        assert!(!rbac.can(&membership("viewer", None), &PermissionAction::Edit, "dev"));
+        assert!(!rbac.can(
+            &membership("no-access", None),
+            &PermissionAction::Read,
+            "dev"
+        ));
+        assert!(!rbac.can(
+            &membership("custom", Some("missing")),
+            &PermissionAction::Read,
+            "dev"
+        ));
+    }
+
+    #[test]
+    fn listed_roles_override_defaults_by_slug() {
+        let mut viewer = deployer();
+        viewer.id = None;
+        viewer.slug = "viewer".to_string();
+        let rbac = Rbac::new(vec![viewer]);
+
+        assert!(rbac.can(
+            &membership("viewer", None),
+            &PermissionAction::Edit,
+            "staging"
+        ));
+    }
+
+    #[test]
+    fn unknown_environment_conditions_forbid_but_never_allow() {
+        let mut role = deployer();
+        role.permissions = vec![
+            RolePermission::allow(PermissionAction::Read, SECRETS_SUBJECT),
+            RolePermission {
+                conditions: Some(json!({ "environment": { "$glob": "prod-*" } })),
+                ..RolePermission::allow(PermissionAction::Edit, SECRETS_SUBJECT)
+            },
+            RolePermission {
+                inverted: true,
+                conditions: Some(json!({ "environment": { "$glob": "prod-*" } })),
+                ..RolePermission::allow(PermissionAction::Read, SECRETS_SUBJECT)
+            },
+        ];
+        let rbac = Rbac::new(vec![role]);
+        let membership = membership("custom", Some("role-id"));
+
+        assert!(!rbac.can(&membership, &PermissionAction::Read, "prod-eu"));
+        assert!(!rbac.can(&membership, &PermissionAction::Edit, "prod-eu"));
+    }
+
+    #[test]
+    fn environment_conditions_support_operators() {
+        assert_eq!(
+            applies_to(Some(&json!({ "environment": { "$eq": "dev" } })), "dev"),
+            Some(true)
+        );
+        assert_eq!(
+            applies_to(
+                Some(&json!({ "environment": { "$in": ["dev", "prod"] } })),
+                "prod"
+            ),
+            Some(true)
+        );
+        assert_eq!(
+            applies_to(Some(&json!({ "environment": "prod" })), "dev"),
+            Some(false)
+        );
+        assert_eq!(
+            applies_to(Some(&json!({ "environment": { "$glob": "*" } })), "dev"),
+            None
+        );
+        assert_eq!(applies_to(None, "dev"), Some(true));
+        assert_eq!(applies_to(Some(&json!({})), "dev"), Some(true));
+    }
+
+    #[test]
+    fn other_conditions_are_not_evaluated() {
+        assert_eq!(applies_to(Some(&json!({ "secretPath": "/" })), "dev"), None);
+        assert_eq!(
+            applies_to(
+                Some(&json!({ "environment": "dev", "secretPath": "/app" })),
+                "dev"
+            ),
+            None
+        );
+        assert_eq!(
+            applies_to(
+                Some(&json!({ "environment": "prod", "secretPath": "/app" })),
+                "dev"
+            ),
+            Some(false)
+        );
+    }
+
+    #[test]
+    fn path_scoped_rules_forbid_but_never_allow() {
+        let mut role = deployer();
+        role.permissions = vec![
+            RolePermission {
+                conditions: Some(json!({ "secretPath": "/app" })),
+                ..RolePermission::allow(PermissionAction::Edit, SECRETS_SUBJECT)
+            },
+            RolePermission::allow(PermissionAction::Read, SECRETS_SUBJECT),
+            RolePermission {
+                inverted: true,
+                conditions: Some(json!({ "environment": "prod", "secretPath": "/admin" })),
+                ..RolePermission::allow(PermissionAction::Read, SECRETS_SUBJECT)
+            },
+        ];
+        let rbac = Rbac::new(vec![role]);
+        let membership = membership("custom", Some("role-id"));
+
+        assert!(!rbac.can(&membership, &PermissionAction::Edit, "dev"));
+        assert!(!rbac.can(&membership, &PermissionAction::Read, "prod"));
+        assert!(rbac.can(&membership, &PermissionAction::Read, "dev"));
+    }
+}
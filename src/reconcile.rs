@@ -0,0 +1,755 @@
+//! Declarative management of projects with [`Client::plan`] and [`Client::apply`]
+//!
+//! A [`Spec`] describes the projects of an organization: their environments, their members and
+//! the keys (not the values) of their secrets. [`Client::plan`] compares it with the live state
+//! of the organization and returns a [`Plan`] of the changes needed to match it, which can be
+//! reviewed, saved as JSON, loaded back and then carried out with [`Client::apply`].
+//!
+//! Only what the spec describes is managed. Projects that are not in the spec are left alone, and
+//! so are the environments, members or secrets of a project whose spec leaves them out. New
+//! secrets are created with an empty value.
+//!
+//! ```yaml
+//! organization: 6420a3b4c5d6e7f8a9b0c1d2
+//! projects:
+//!   - name: backend
+//!     environments:
+//!       - { name: Development, slug: dev }
+//!       - { name: Production, slug: prod }
+//!     members:
+//!       - { email: jane@example.com, role: admin }
+//!     secrets:
+//!       dev: [DATABASE_URL, API_KEY]
+//! ```
+//!
+//! [`Client::plan`]: crate::Client::plan
+//! [`Client::apply`]: crate::Client::apply
+
+use std::collections::{BTreeMap, HashSet};
+
+use serde::{Deserialize, Serialize};
+
+use crate::api::models::{Environment, ProjectMembership, Role};
+use crate::error::{self, Result};
+
+/// The desired state of the projects of an organization
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct Spec {
+    /// The id of the organization the projects belong to
+    pub organization: String,
+    /// The managed projects, identified by name
+    #[serde(default)]
+    pub projects: Vec<ProjectSpec>,
+}
+
+/// The desired state of a project
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct ProjectSpec {
+    /// The name of the project
+    pub name: String,
+    /// The environments of the project in order, or `None` to leave them unmanaged
+    pub environments: Option<Vec<EnvironmentSpec>>,
+    /// The members of the project, or `None` to leave them unmanaged
+    pub members: Option<Vec<MemberSpec>>,
+    /// The keys of the shared secrets of each environment, or `None` to leave them unmanaged
+    ///
+    /// Environments that are not listed are left unmanaged.
+    pub secrets: Option<BTreeMap<String, Vec<String>>>,
+}
+
+/// The desired state of an environment, identified by its slug
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct EnvironmentSpec {
+    /// The display name of the environment
+    pub name: String,
+    /// The slug of the environment
+    pub slug: String,
+}
+
+/// The desired state of a project member, identified by their email
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct MemberSpec {
+    /// The email of the member
+    pub email: String,
+    /// The role of the member, [`Role::Member`] by default
+    #[serde(default = "default_role")]
+    pub role: Role,
+}
+
+fn default_role() -> Role {
+    Role::Member
+}
+
+impl Spec {
+    /// Parses a spec from YAML
+    #[cfg(feature = "yaml")]
+    pub fn from_yaml(value: &str) -> Result<Spec> {
+        serde_yaml::from_str(value).map_err(|e| error::invalid_input("spec", e))
+    }
+
+    /// Parses a spec from TOML
+    #[cfg(feature = "toml")]
+    pub fn from_toml(value: &str) -> Result<Spec> {
+        toml::from_str(value).map_err(|e| error::invalid_input("spec", e))
+    }
+
+    /// Checks that the spec is consistent before anything is requested from Infisical
+    ///
+    /// Project names, environment slugs and member emails must be unique, slugs must be valid,
+    /// and secrets may only be listed for environments of the spec when it lists environments.
+    pub fn validate(&self) -> Result<()> {
+        let invalid = |reason: String| error::invalid_input("spec", reason);
+
+        let mut names = HashSet::new();
+        for project in &self.projects {
+            if !names.insert(project.name.as_str()) {
+                return Err(invalid(format!("duplicate project {}", project.name)));
+            }
+
+            if let Some(environments) = &project.environments {
+                let mut slugs = HashSet::new();
+                for environment in environments {
+                    Environment::validate_slug(&environment.slug)?;
+                    if !slugs.insert(environment.slug.as_str()) {
+                        return Err(invalid(format!(
+                            "duplicate environment {} in project {}",
+                            environment.slug, project.name
+                        )));
+                    }
+                }
+
+                for environment in project.secrets.iter().flat_map(|secrets| secrets.keys()) {
+                    if !slugs.contains(environment.as_str()) {
+                        return Err(invalid(format!(
+                            "secrets listed for unknown environment {} in project {}",
+                            environment, project.name
+                        )));
+                    }
+                }
+            }
+
+            let mut emails = HashSet::new();
+            for member in project.members.iter().flatten() {
+                if !emails.insert(member.email.to_lowercase()) {
+                    return Err(invalid(format!(
+                        "duplicate member {} in project {}",
+                        member.email, project.name
+                    )));
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// The changes needed to make an organization match a [`Spec`]
+///
+/// A plan can be saved with [`Plan::to_json`] and loaded back with [`Plan::from_json`], so that
+/// the plan that was reviewed is the one that is applied.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+pub struct Plan {
+    /// The projects with changes, in the order of the spec
+    pub projects: Vec<ProjectPlan>,
+}
+
+impl Plan {
+    /// Returns true if the organization already matches the spec
+    pub fn is_empty(&self) -> bool {
+        self.projects.is_empty()
+    }
+
+    /// Returns the plan as JSON, for review or for other tools
+    pub fn to_json(&self) -> String {
+        // Serializing a tree of strings can't fail
+        serde_json::to_string_pretty(self).unwrap_or_default()
+    }
+
+    /// Parses a plan from the JSON produced by [`Plan::to_json`]
+    pub fn from_json(value: &str) -> Result<Plan> {
+        serde_json::from_str(value).map_err(|e| error::invalid_input("plan", e))
+    }
+}
+
+/// The changes to a single project
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct ProjectPlan {
+    /// The name of the project
+    pub name: String,
+    /// The id of the project, or `None` if it is to be created
+    pub id: Option<String>,
+    /// The changes, in the order they are applied
+    pub changes: Vec<Change>,
+}
+
+/// A single change to a project
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(tag = "action", rename_all = "snake_case")]
+#[non_exhaustive]
+pub enum Change {
+    /// Create the project
+    CreateProject,
+    /// Create an environment
+    CreateEnvironment {
+        /// The display name of the environment
+        name: String,
+        /// The slug of the environment
+        slug: String,
+    },
+    /// Change the display name of an environment
+    RenameEnvironment {
+        /// The slug of the environment
+        slug: String,
+        /// The new display name
+        name: String,
+    },
+    /// Delete an environment along with its secrets
+    DeleteEnvironment {
+        /// The slug of the environment
+        slug: String,
+    },
+    /// Reorder the environments
+    ReorderEnvironments {
+        /// The slugs of the environments in their new order
+        slugs: Vec<String>,
+    },
+    /// Create a shared secret with an empty value
+    CreateSecret {
+        /// The slug of the environment
+        environment: String,
+        /// The key of the secret
+        key: String,
+    },
+    /// Delete a shared secret
+    DeleteSecret {
+        /// The slug of the environment
+        environment: String,
+        /// The key of the secret
+        key: String,
+        /// The id of the secret
+        id: String,
+    },
+    /// Add a member and share the project key with them
+    AddMember {
+        /// The email of the member
+        email: String,
+        /// The role of the member
+        role: Role,
+    },
+    /// Change the role of a member
+    UpdateMemberRole {
+        /// The email of the member
+        email: String,
+        /// The new role
+        role: Role,
+        /// The id of the membership
+        membership_id: String,
+    },
+    /// Remove a member
+    RemoveMember {
+        /// The email of the member
+        email: String,
+        /// The id of the membership
+        membership_id: String,
+    },
+}
+
+/// The live state of a project, limited to what its spec manages
+pub(crate) struct LiveProject {
+    pub environments: Vec<Environment>,
+    pub memberships: Vec<ProjectMembership>,
+    /// The id and key of the shared secrets of each environment listed in the spec
+    pub secrets: BTreeMap<String, Vec<(String, String)>>,
+}
+
+/// The environments Infisical gives every new project
+fn default_environments() -> Vec<Environment> {
+    [
+        ("Development", "dev"),
+        ("Staging", "staging"),
+        ("Production", "prod"),
+    ]
+    .into_iter()
+    .map(|(name, slug)| Environment {
+        name: name.to_string(),
+        slug: slug.to_string(),
+    })
+    .collect()
+}
+
+/// Computes the changes that make `live` match `spec`, or that create the project if `live` is
+/// `None`
+///
+/// A new project starts out with Infisical's default environments, so the changes to those are
+/// included. `current_user_email` is never removed, so applying a plan can't lock out the user
+/// applying it. Emails are compared case-insensitively.
+pub(crate) fn diff_project(
+    spec: &ProjectSpec,
+    live: Option<&LiveProject>,
+    current_user_email: &str,
+) -> Vec<Change> {
+    let new_project = LiveProject {
+        environments: default_environments(),
+        memberships: Vec::new(),
+        secrets: BTreeMap::new(),
+    };
+    let mut changes = Vec::new();
+
+    if live.is_none() {
+        changes.push(Change::CreateProject);
+    }
+    let live = live.unwrap_or(&new_project);
+
+    let mut deleted_environments = HashSet::new();
+    if let Some(environments) = &spec.environments {
+        let mut order: Vec<&str> = Vec::new();
+
+        for environment in &live.environments {
+            match environments.iter().find(|e| e.slug == environment.slug) {
+                Some(wanted) => {
+                    if wanted.name != environment.name {
+                        changes.push(Change::RenameEnvironment {
+                            slug: wanted.slug.clone(),
+                            name: wanted.name.clone(),
+                        });
+                    }
+                    order.push(&environment.slug);
+                }
+                None => {
+                    deleted_environments.insert(environment.slug.as_str());
+                    changes.push(Change::DeleteEnvironment {
+                        slug: environment.slug.clone(),
+                    });
+                }
+            }
+        }
+
+        for environment in environments {
+            if !live.environments.iter().any(|e| e.slug == environment.slug) {
+                changes.push(Change::CreateEnvironment {
+                    name: environment.name.clone(),
+                    slug: environment.slug.clone(),
+                });
+                order.push(&environment.slug);
+            }
+        }
+
+        let wanted_order: Vec<&str> = environments.iter().map(|e| e.slug.as_str()).collect();
+        if order != wanted_order {
+            changes.push(Change::ReorderEnvironments {
+                slugs: wanted_order.iter().map(|slug| slug.to_string()).collect(),
+            });
+        }
+    }
+
+    for (environment, keys) in spec.secrets.iter().flatten() {
+        if deleted_environments.contains(environment.as_str()) {
+            continue;
+        }
+        let live_secrets = live
+            .secrets
+            .get(environment)
+            .map(Vec::as_slice)
+            .unwrap_or(&[]);
+
+        for key in keys {
+            if !live_secrets.iter().any(|(_, live_key)| live_key == key) {
+                changes.push(Change::CreateSecret {
+                    environment: environment.clone(),
+                    key: key.clone(),
+                });
+            }
+        }
+
+        for (id, key) in live_secrets {
+            if !keys.contains(key) {
+                changes.push(Change::DeleteSecret {
+                    environment: environment.clone(),
+                    key: key.clone(),
+                    id: id.clone(),
+                });
+            }
+        }
+    }
+
+    if let Some(members) = &spec.members {
+        for member in members {
+            match live
+                .memberships
+                .iter()
+                .find(|m| same_email(&m.user.email, &member.email))
+            {
+                Some(membership) if membership.role != member.role => {
+                    changes.push(Change::UpdateMemberRole {
+                        email: member.email.clone(),
+                        role: member.role.clone(),
+                        membership_id: membership.id.clone(),
+                    })
+                }
+                Some(_) => {}
+                // The creator of a new project is already a member of it
+                None if is_new_project(&changes)
+                    && same_email(&member.email, current_user_email) => {}
+                None => changes.push(Change::AddMember {
+                    email: member.email.clone(),
+                    role: member.role.clone(),
+                }),
+            }
+        }
+
+        for membership in &live.memberships {
+            if !same_email(&membership.user.email, current_user_email)
+                && !members
+                    .iter()
+                    .any(|m| same_email(&m.email, &membership.user.email))
+            {
+                changes.push(Change::RemoveMember {
+                    email: membership.user.email.clone(),
+                    membership_id: membership.id.clone(),
+                });
+            }
+        }
+    }
+
+    changes
+}
+
+fn is_new_project(changes: &[Change]) -> bool {
+    changes.first() == Some(&Change::CreateProject)
+}
+
+fn same_email(a: &str, b: &str) -> bool {
+    a.to_lowercase() == b.to_lowercase()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn environment(name: &str, slug: &str) -> Environment {
+        Environment {
+            name: name.to_string(),
+            slug: slug.to_string(),
+        }
+    }
+
+    fn membership(id: &str, email: &str, role: &str) -> ProjectMembership {
+        serde_json::from_value(json!({
+            "_id": id,
+            "role": role,
+            "workspace": "workspace-id",
+            "deniedPermissions": [],
+            "user": {
+                "_id": format!("user-{}", id),
+                "email": email,
+                "firstName": "Jane",
+                "lastName": "Doe",
+                "__v": 0,
+                "devices": [],
+                "isMfaEnabled": false,
+                "mfaMethods": [],
+                "createdAt": "2023-04-01T00:00:00.000Z",
+                "updatedAt": "2023-04-01T00:00:00.000Z",
+            },
+            "createdAt": "2023-04-01T00:00:00.000Z",
+            "updatedAt": "2023-04-01T00:00:00.000Z",
+        }))
+        .unwrap()
+    }
+
+    fn spec() -> ProjectSpec {
+        serde_json::from_value(json!({
+            "name": "backend",
+            "environments": [
+                { "name": "Development", "slug": "dev" },
+                { "name": "Production", "slug": "prod" },
+            ],
+            "members": [
+                { "email": "me@example.com", "role": "admin" },
+                { "email": "jane@example.com" },
+            ],
+            "secrets": { "dev": ["DATABASE_URL", "API_KEY"] },
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn new_project_is_created() {
+        let changes = diff_project(&spec(), None, "me@example.com");
+
+        assert_eq!(
+            changes,
+            [
+                Change::CreateProject,
+                Change::DeleteEnvironment {
+                    slug: "staging".to_string()
+                },
+                Change::CreateSecret {
+                    environment: "dev".to_string(),
+                    key: "DATABASE_URL".to_string()
+                },
+                Change::CreateSecret {
+                    environment: "dev".to_string(),
+                    key: "API_KEY".to_string()
+                },
+                Change::AddMember {
+                    email: "jane@example.com".to_string(),
+                    role: Role::Member
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn live_project_is_reconciled() {
+        let live = LiveProject {
+            environments: vec![
+                environment("Prod", "prod"),
+                environment("Staging", "staging"),
+                environment("Development", "dev"),
+            ],
+            memberships: vec![
+                membership("1", "me@example.com", "admin"),
+                membership("2", "jane@example.com", "admin"),
+                membership("3", "former@example.com", "member"),
+            ],
+            secrets: BTreeMap::from([(
+                "dev".to_string(),
+                vec![
+                    ("secret-1".to_string(), "DATABASE_URL".to_string()),
+                    ("secret-2".to_string(), "OLD_KEY".to_string()),
+                ],
+            )]),
+        };
+
+        let changes = diff_project(&spec(), Some(&live), "me@example.com");
+
+        assert_eq!(
+            changes,
+            [
+                Change::RenameEnvironment {
+                    slug: "prod".to_string(),
+                    name: "Production".to_string()
+                },
+                Change::DeleteEnvironment {
+                    slug: "staging".to_string()
+                },
+                Change::ReorderEnvironments {
+                    slugs: vec!["dev".to_string(), "prod".to_string()]
+                },
+                Change::CreateSecret {
+                    environment: "dev".to_string(),
+                    key: "API_KEY".to_string()
+                },
+                Change::DeleteSecret {
+                    environment: "dev".to_string(),
+                    key: "OLD_KEY".to_string(),
+                    id: "secret-2".to_string()
+                },
+                Change::UpdateMemberRole {
+                    email: "jane@example.com".to_string(),
+                    role: Role::Member,
+                    membership_id: "2".to_string()
+                },
+                Change::RemoveMember {
+                    email: "former@example.com".to_string(),
+                    membership_id: "3".to_string()
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn emails_are_compared_case_insensitively() {
+        let mut spec = spec();
+        spec.environments = None;
+        spec.secrets = None;
+        let live = LiveProject {
+            environments: Vec::new(),
+            memberships: vec![
+                membership("1", "Me@Example.com", "admin"),
+                membership("2", "Jane@Example.com", "member"),
+            ],
+            secrets: BTreeMap::new(),
+        };
+
+        assert!(diff_project(&spec, Some(&live), "me@example.com").is_empty());
+
+        spec.members = Some(vec![
+            MemberSpec {
+                email: "jane@example.com".to_string(),
+                role: Role::Member,
+            },
+            MemberSpec {
+                email: "JANE@example.com".to_string(),
+                role: Role::Member,
+            },
+        ]);
+        let spec = Spec {
+            organization: "organization-id".to_string(),
+            projects: vec![spec],
+        };
+        assert!(spec.validate().unwrap_err().is_invalid_input());
+    }
+
+    #[test]
+    fn unmanaged_parts_are_left_alone() {
+        let spec = ProjectSpec {
+            name: "backend".to_string(),
+            environments: None,
+            members: None,
+            secrets: None,
+        };
+        let live = LiveProject {
+            environments: vec![environment("Development", "dev")],
+            memberships: vec![membership("1", "jane@example.com", "admin")],
+            secrets: BTreeMap::new(),
+        };
+
+        assert!(diff_project(&spec, Some(&live), "me@example.com").is_empty());
+    }
+
+    #[test]
+    fn current_user_is_never_removed() {
+        let mut spec = spec();
+        spec.members = Some(Vec::new());
+        let live = LiveProject {
+            environments: vec![
+                environment("Development", "dev"),
+                environment("Production", "prod"),
+            ],
+            memberships: vec![membership("1", "me@example.com", "admin")],
+            secrets: BTreeMap::from([(
+                "dev".to_string(),
+                vec![
+                    ("secret-1".to_string(), "DATABASE_URL".to_string()),
+                    ("secret-2".to_string(), "API_KEY".to_string()),
+                ],
+            )]),
+        };
+
+        assert!(diff_project(&spec, Some(&live), "me@example.com").is_empty());
+    }
+
+    #[test]
+    fn spec_is_validated() {
+        let mut spec = Spec {
+            organization: "organization-id".to_string(),
+            projects: vec![spec()],
+        };
+        assert!(spec.validate().is_ok());
+
+        spec.projects[0].secrets = Some(BTreeMap::from([("qa".to_string(), Vec::new())]));
+        assert!(spec.validate().unwrap_err().is_invalid_input());
+
+        spec.projects[0].secrets = None;
+        spec.projects[0].environments = Some(vec![EnvironmentSpec {
+            name: "Preview".to_string(),
+            slug: "Preview".to_string(),
+        }]);
+        assert!(spec.validate().unwrap_err().is_invalid_input());
+
+        spec.projects = vec![self::spec(), self::spec()];
+        assert!(spec.validate().unwrap_err().is_invalid_input());
+    }
+
+    #[test]
+    fn plan_round_trips_through_json() {
+        let plan = Plan {
+            projects: vec![ProjectPlan {
+                name: "backend".to_string(),
+                id: Some("workspace-id".to_string()),
+                changes: vec![
+                    Change::ReorderEnvironments {
+                        slugs: vec!["dev".to_string(), "prod".to_string()],
+                    },
+                    Change::UpdateMemberRole {
+                        email: "jane@example.com".to_string(),
+                        role: Role::Admin,
+                        membership_id: "membership-id".to_string(),
+                    },
+                ],
+            }],
+        };
+
+        assert_eq!(Plan::from_json(&plan.to_json()).unwrap(), plan);
+        assert!(Plan::from_json("{}").unwrap_err().is_invalid_input());
+    }
+
+    #[test]
+    fn plan_serializes_changes_with_action_tags() {
+        let plan = Plan {
+            projects: vec![ProjectPlan {
+                name: "backend".to_string(),
+                id: None,
+                changes: vec![Change::CreateProject],
+            }],
+        };
+
+        let json: serde_json::Value = serde_json::from_str(&plan.to_json()).unwrap();
+
+        assert_eq!(
+            json,
+            json!({
+                "projects": [{
+                    "name": "backend",
+                    "id": null,
+                    "changes": [{ "action": "create_project" }],
+                }],
+            })
+        );
+    }
+
+    #[cfg(feature = "yaml")]
+    #[test]
+    fn spec_parses_from_yaml() {
+        let spec = Spec::from_yaml(
+            "organization: organization-id\n\
+             projects:\n\
+             \x20 - name: backend\n\
+             \x20   environments:\n\
+             \x20     - { name: Development, slug: dev }\n\
+             \x20   members:\n\
+             \x20     - { email: jane@example.com }\n",
+        )
+        .unwrap();
+
+        assert_eq!(
+            spec.projects[0].environments.as_ref().unwrap()[0].slug,
+            "dev"
+        );
+        assert_eq!(
+            spec.projects[0].members.as_ref().unwrap()[0].role,
+            Role::Member
+        );
+        assert!(spec.projects[0].secrets.is_none());
+    }
+
+    #[cfg(feature = "toml")]
+    #[test]
+    fn spec_parses_from_toml() {
+        let spec = Spec::from_toml(
+            r#"
+            organization = "organization-id"
+
+            [[projects]]
+            name = "backend"
+            environments = [{ name = "Development", slug = "dev" }]
+
+            [projects.secrets]
+            dev = ["DATABASE_URL"]
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            spec.projects[0].secrets.as_ref().unwrap()["dev"],
+            ["DATABASE_URL"]
+        );
+    }
+}
@@ -0,0 +1,138 @@
+//! Types for rotating the key of an Infisical project with [`Client::rotate_project_key`]
+//!
+//! Rotating a project key re-encrypts every secret of every environment with a new key and then
+//! shares the new key with every member of the project. Infisical has no way to do this in a
+//! single request, so a rotation is made of one request per environment followed by one request
+//! per member. A [`RotationCheckpoint`] records which of those have completed, so that a rotation
+//! that failed half way can be resumed with the same new key.
+//!
+//! [`Client::rotate_project_key`]: crate::Client::rotate_project_key
+
+use crate::keys::ProjectKey;
+
+/// The state of a project key rotation
+///
+/// Pass the same checkpoint to [`Client::rotate_project_key`] to resume a rotation that failed.
+/// Environments and members that were already completed are skipped. The checkpoint holds the new
+/// project key, so it must be stored as carefully as the key itself.
+///
+/// [`Client::rotate_project_key`]: crate::Client::rotate_project_key
+#[derive(Clone, Debug)]
+pub struct RotationCheckpoint {
+    new_key: ProjectKey,
+    completed_environments: Vec<String>,
+    shared_with: Vec<String>,
+}
+
+impl RotationCheckpoint {
+    /// Starts a new rotation to a randomly generated project key
+    pub fn new() -> RotationCheckpoint {
+        RotationCheckpoint::resume(ProjectKey::generate(), Vec::new(), Vec::new())
+    }
+
+    /// Restores a checkpoint from its parts, for example after loading them from storage
+    pub fn resume(
+        new_key: ProjectKey,
+        completed_environments: Vec<String>,
+        shared_with: Vec<String>,
+    ) -> RotationCheckpoint {
+        RotationCheckpoint {
+            new_key,
+            completed_environments,
+            shared_with,
+        }
+    }
+
+    /// Returns the key the project is rotated to
+    pub fn new_key(&self) -> &ProjectKey {
+        &self.new_key
+    }
+
+    /// Returns the slugs of the environments whose secrets are encrypted with the new key
+    pub fn completed_environments(&self) -> &[String] {
+        &self.completed_environments
+    }
+
+    /// Returns the ids of the users the new key was shared with
+    pub fn shared_with(&self) -> &[String] {
+        &self.shared_with
+    }
+
+    pub(crate) fn is_environment_completed(&self, environment: &str) -> bool {
+        self.completed_environments.iter().any(|e| e == environment)
+    }
+
+    pub(crate) fn is_shared_with(&self, user_id: &str) -> bool {
+        self.shared_with.iter().any(|u| u == user_id)
+    }
+
+    pub(crate) fn complete_environment(&mut self, environment: &str) {
+        self.completed_environments.push(environment.to_string());
+    }
+
+    pub(crate) fn complete_share(&mut self, user_id: &str) {
+        self.shared_with.push(user_id.to_string());
+    }
+}
+
+impl Default for RotationCheckpoint {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A step of a project key rotation, reported as the rotation progresses
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum RotationProgress {
+    /// The secrets of an environment were re-encrypted with the new key
+    EnvironmentReencrypted {
+        /// The slug of the environment
+        environment: String,
+        /// The number of secrets that were re-encrypted
+        secrets: usize,
+    },
+    /// An environment was skipped because the checkpoint shows it was already re-encrypted
+    EnvironmentSkipped {
+        /// The slug of the environment
+        environment: String,
+    },
+    /// The new key was shared with a project member
+    KeyShared {
+        /// The id of the member's user
+        user_id: String,
+    },
+    /// A project member was skipped because the checkpoint shows they already have the new key
+    MemberSkipped {
+        /// The id of the member's user
+        user_id: String,
+    },
+    /// A project member could not be given the new key because they have no public key yet
+    MemberWithoutPublicKey {
+        /// The id of the member's user
+        user_id: String,
+    },
+    /// A project member could not be given the new key because their public key is malformed
+    MemberWithInvalidPublicKey {
+        /// The id of the member's user
+        user_id: String,
+    },
+}
+
+/// The outcome of a project key rotation
+#[derive(Clone, Debug, Default)]
+pub struct RotationReport {
+    /// Whether this was a dry run, in which case nothing was uploaded
+    pub dry_run: bool,
+    /// The number of secrets that were (or, for a dry run, would be) re-encrypted
+    pub reencrypted_secrets: usize,
+    /// The number of secrets that were skipped because they were already encrypted with the new
+    /// key by an earlier attempt
+    pub already_reencrypted_secrets: usize,
+    /// The ids of the users the new key was (or would be) shared with
+    pub shared_with: Vec<String>,
+    /// The ids of the members that have no public key and so could not be given the new key
+    pub members_without_public_key: Vec<String>,
+    /// The ids of the members whose public key is malformed and so could not be given the new key
+    pub members_with_invalid_public_key: Vec<String>,
+}
@@ -0,0 +1,113 @@
+//! Wrappers for sensitive values such as private keys, project keys and decrypted secrets
+//!
+//! The wrapped values are zeroized when they are dropped and are redacted when formatted with
+//! `Debug` or `Display`, so they don't end up in logs by accident. They can only be read through
+//! an explicit call to `expose`.
+
+use std::fmt;
+
+use zeroize::Zeroize;
+
+/// A `String` that is zeroized on drop and redacted when formatted
+#[derive(Clone, Default)]
+pub struct SecretString(String);
+
+impl SecretString {
+    /// Wraps `value` in a `SecretString`
+    pub fn new(value: String) -> SecretString {
+        SecretString(value)
+    }
+
+    /// Returns the secret value
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+}
+
+impl From<String> for SecretString {
+    fn from(value: String) -> SecretString {
+        SecretString::new(value)
+    }
+}
+
+impl From<&str> for SecretString {
+    fn from(value: &str) -> SecretString {
+        SecretString::new(value.to_string())
+    }
+}
+
+impl Drop for SecretString {
+    fn drop(&mut self) {
+        self.0.zeroize();
+    }
+}
+
+impl fmt::Debug for SecretString {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("SecretString([REDACTED])")
+    }
+}
+
+impl fmt::Display for SecretString {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("[REDACTED]")
+    }
+}
+
+/// A byte buffer that is zeroized on drop and redacted when formatted
+#[derive(Clone, Default)]
+pub struct SecretBytes(Vec<u8>);
+
+impl SecretBytes {
+    /// Wraps `value` in a `SecretBytes`
+    pub fn new(value: Vec<u8>) -> SecretBytes {
+        SecretBytes(value)
+    }
+
+    /// Returns the secret bytes
+    pub fn expose(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl From<Vec<u8>> for SecretBytes {
+    fn from(value: Vec<u8>) -> SecretBytes {
+        SecretBytes::new(value)
+    }
+}
+
+impl From<&[u8]> for SecretBytes {
+    fn from(value: &[u8]) -> SecretBytes {
+        SecretBytes::new(value.to_vec())
+    }
+}
+
+impl Drop for SecretBytes {
+    fn drop(&mut self) {
+        self.0.zeroize();
+    }
+}
+
+impl fmt::Debug for SecretBytes {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("SecretBytes([REDACTED])")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn formatting_is_redacted() {
+        let secret = SecretString::from("hunter2");
+
+        assert_eq!(format!("{:?}", secret), "SecretString([REDACTED])");
+        assert_eq!(secret.to_string(), "[REDACTED]");
+        assert_eq!(secret.expose(), "hunter2");
+        assert_eq!(
+            format!("{:?}", SecretBytes::from(&b"hunter2"[..])),
+            "SecretBytes([REDACTED])"
+        );
+    }
+}
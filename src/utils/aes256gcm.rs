@@ -1,28 +1,120 @@
-use crate::error::Result;
-use crate::utils::base64;
+//! AES-256-GCM encryption with the 16 byte nonces used by Infisical
+//!
+//! Keys are given as an [`AesKey`], which can only be constructed with exactly 32 bytes. Infisical
+//! itself uses strings as keys: project keys are 32 hexadecimal characters whose UTF-8 bytes are
+//! the key, and the private keys of legacy users are encrypted with their password left-padded
+//! with `0`s to 32 characters. [`AesKey::from_legacy`] derives keys that way, and [`encrypt`] and
+//! [`decrypt`] use it for compatibility with existing callers.
+
+use std::fmt;
+
+use crate::error::{self, Result};
+use crate::utils::{base64, hex};
 use aes_gcm::{
     aead::{generic_array::typenum::U16, Aead, AeadCore, KeyInit, OsRng},
     aes::Aes256,
     AesGcm, Nonce,
 };
+use zeroize::Zeroize;
+
+/// The length of the authentication tag that AES-GCM appends to the ciphertext
+const TAG_SIZE: usize = 16;
+
+/// A 256 bit AES-GCM key
+///
+/// The key is zeroized on drop and redacted when formatted with `Debug`.
+#[derive(Clone)]
+pub struct AesKey([u8; 32]);
+
+impl AesKey {
+    /// Constructs an `AesKey` from its raw bytes
+    pub fn from_bytes(bytes: [u8; 32]) -> AesKey {
+        AesKey(bytes)
+    }
+
+    /// Constructs an `AesKey` from a slice, which must be exactly 32 bytes long
+    pub fn from_slice(bytes: &[u8]) -> Result<AesKey> {
+        <[u8; 32]>::try_from(bytes)
+            .map(AesKey)
+            .map_err(|_| error::decode("key", format!("expected 32 bytes, got {}", bytes.len())))
+    }
+
+    /// Constructs an `AesKey` from 64 hexadecimal characters
+    pub fn from_hex(value: &str) -> Result<AesKey> {
+        hex::decode_array::<32>("key", value).map(AesKey)
+    }
+
+    /// Constructs an `AesKey` from 32 base64 encoded bytes
+    pub fn from_base64(value: &str) -> Result<AesKey> {
+        base64::decode_array::<32>("key", value).map(AesKey)
+    }
+
+    /// Constructs an `AesKey` from a string the way Infisical does for project keys and the
+    /// passwords of legacy users
+    ///
+    /// The string is left-padded with `0`s or truncated to 32 characters and its UTF-8 bytes are
+    /// used as the key. Strings with multibyte characters that don't come out at exactly 32 bytes
+    /// are rejected.
+    pub fn from_legacy(secret: &str) -> Result<AesKey> {
+        let mut padded = format!("{:0>len$.len$}", secret, len = 32);
+        let key = AesKey::from_slice(padded.as_bytes()).map_err(|_| {
+            error::decode(
+                "key",
+                format!("expected 32 bytes after padding, got {}", padded.len()),
+            )
+        });
+        padded.zeroize();
+
+        key
+    }
+
+    /// Returns the raw bytes of the key
+    pub fn as_bytes(&self) -> &[u8; 32] {
+        &self.0
+    }
+
+    fn cipher(&self) -> AesGcm<Aes256, U16> {
+        AesGcm::new(&self.0.into())
+    }
+}
+
+impl Drop for AesKey {
+    fn drop(&mut self) {
+        self.0.zeroize();
+    }
+}
+
+impl fmt::Debug for AesKey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("AesKey([REDACTED])")
+    }
+}
 
+/// Encrypts `text` with a key derived from `secret` by [`AesKey::from_legacy`]
 pub fn encrypt(text: &str, secret: &str) -> Result<Encryption> {
+    encrypt_bytes(text.as_bytes(), &AesKey::from_legacy(secret)?)
+}
+
+/// Encrypts `plaintext` with a random nonce
+pub fn encrypt_bytes(plaintext: &[u8], key: &AesKey) -> Result<Encryption> {
     let nonce = AesGcm::<Aes256, U16>::generate_nonce(&mut OsRng);
-    let cipher: AesGcm<Aes256, U16> =
-        AesGcm::new_from_slice(format!("{:0>len$.len$}", secret, len = 32).as_bytes())?;
-    let ciphertext = cipher.encrypt(&nonce, text.as_bytes())?;
+    let mut ciphertext = key
+        .cipher()
+        .encrypt(&nonce, plaintext)
+        .map_err(|_| error::encrypt("the plaintext is too long"))?;
 
-    // The authentication tag is automatically appended by cipher.encrypt but we need to pass it to
-    // infisical. So here we extract it as a slice of the ciphertext.
-    let tag = &ciphertext[text.len()..];
+    // The authentication tag is appended to the ciphertext by cipher.encrypt but Infisical
+    // expects it separately, so it is split off here.
+    let tag = ciphertext.split_off(ciphertext.len() - TAG_SIZE);
 
     Ok(Encryption {
-        text: base64::encode(&ciphertext[..text.len()]),
-        tag: base64::encode(tag),
+        text: base64::encode(&ciphertext),
+        tag: base64::encode(&tag),
         nonce: base64::encode(nonce.as_slice()),
     })
 }
 
+/// A base64 encoded ciphertext along with the nonce and authentication tag needed to decrypt it
 #[derive(Debug)]
 pub struct Encryption {
     pub text: String,
@@ -30,27 +122,29 @@ pub struct Encryption {
     pub nonce: String,
 }
 
+/// Decrypts UTF-8 text with a key derived from `secret` by [`AesKey::from_legacy`]
 pub fn decrypt(text: &str, nonce: &str, tag: &str, secret: &str) -> Result<String> {
-    let nonce = base64::decode(nonce);
-    let tag = base64::decode(tag);
-    let text = base64::decode(text);
-    let nonce = Nonce::<U16>::from_slice(&nonce);
+    let plaintext = decrypt_bytes(text, nonce, tag, &AesKey::from_legacy(secret)?)?;
 
-    let mut ciphertext = text.clone();
-    ciphertext.extend(&tag);
+    String::from_utf8(plaintext).map_err(error::utf8)
+}
 
-    let cipher: AesGcm<Aes256, U16> =
-        AesGcm::new_from_slice(format!("{:0>len$.len$}", secret, len = 32).as_bytes())?;
+/// Decrypts base64 encoded ciphertext with its base64 encoded nonce and authentication tag
+pub fn decrypt_bytes(text: &str, nonce: &str, tag: &str, key: &AesKey) -> Result<Vec<u8>> {
+    let nonce = base64::decode_array::<16>("iv", nonce)?;
+    let tag = base64::decode_array::<TAG_SIZE>("tag", tag)?;
+    let mut ciphertext = base64::decode("ciphertext", text)?;
+    let nonce = Nonce::<U16>::from_slice(&nonce);
 
-    let result = cipher.decrypt(nonce, ciphertext.as_ref())?;
-    let result = String::from_utf8(result).map_err(crate::error::utf8)?;
+    ciphertext.extend(&tag);
 
-    Ok(result)
+    Ok(key.cipher().decrypt(nonce, ciphertext.as_ref())?)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use proptest::prelude::*;
 
     #[test]
     fn encrypt_is_successful() {
@@ -68,4 +162,109 @@ mod tests {
 
         assert_eq!(decrypted_val, val, "Encryption did not provide the expected result upon decryption.\nResult: {}\nExpected: {}", decrypted_val, val);
     }
+
+    #[test]
+    fn decrypt_rejects_short_iv() {
+        let encrypted_val = encrypt("encrypt this text, please.", "secret").unwrap();
+
+        let err = decrypt(
+            &encrypted_val.text,
+            &base64::encode(b"short"),
+            &encrypted_val.tag,
+            "secret",
+        )
+        .unwrap_err();
+
+        assert!(err.is_decode());
+        assert!(err.to_string().contains("iv"));
+    }
+
+    #[test]
+    fn key_rejects_wrong_length() {
+        assert!(AesKey::from_slice(&[1; 32]).is_ok());
+        assert!(AesKey::from_slice(&[1; 31]).unwrap_err().is_decode());
+        assert!(AesKey::from_hex(&"ab".repeat(16)).unwrap_err().is_decode());
+        assert!(AesKey::from_base64(&base64::encode(&[1; 33]))
+            .unwrap_err()
+            .is_decode());
+    }
+
+    #[test]
+    fn key_encodings_agree() {
+        let key = AesKey::from_hex(&"ab".repeat(32)).unwrap();
+
+        assert_eq!(key.as_bytes(), &[0xab; 32]);
+        assert_eq!(
+            AesKey::from_base64(&base64::encode(&[0xab; 32]))
+                .unwrap()
+                .as_bytes(),
+            key.as_bytes()
+        );
+    }
+
+    #[test]
+    fn legacy_key_is_padded_and_truncated() {
+        assert_eq!(
+            AesKey::from_legacy("secret").unwrap().as_bytes(),
+            b"00000000000000000000000000secret"
+        );
+        assert_eq!(
+            AesKey::from_legacy(&"a".repeat(40)).unwrap().as_bytes(),
+            "a".repeat(32).as_bytes()
+        );
+    }
+
+    #[test]
+    fn legacy_key_rejects_multibyte_overflow() {
+        let err = encrypt("text", &"é".repeat(20)).unwrap_err();
+
+        assert!(err.is_decode());
+    }
+
+    #[test]
+    fn key_is_redacted() {
+        assert_eq!(
+            format!("{:?}", AesKey::from_bytes([1; 32])),
+            "AesKey([REDACTED])"
+        );
+    }
+
+    proptest! {
+        #[test]
+        fn decrypt_inverts_encrypt(val in "\\PC*", secret in "[ -~]{0,40}") {
+            let encrypted_val = encrypt(&val, &secret).unwrap();
+            let decrypted_val = decrypt(
+                &encrypted_val.text,
+                &encrypted_val.nonce,
+                &encrypted_val.tag,
+                &secret,
+            )
+            .unwrap();
+
+            prop_assert_eq!(decrypted_val, val);
+        }
+
+        #[test]
+        fn decrypt_bytes_inverts_encrypt_bytes(
+            plaintext in proptest::collection::vec(any::<u8>(), 0..256),
+            key in any::<[u8; 32]>(),
+        ) {
+            let key = AesKey::from_bytes(key);
+            let encrypted = encrypt_bytes(&plaintext, &key).unwrap();
+            let decrypted =
+                decrypt_bytes(&encrypted.text, &encrypted.nonce, &encrypted.tag, &key).unwrap();
+
+            prop_assert_eq!(decrypted, plaintext);
+        }
+
+        #[test]
+        fn decrypt_never_panics(
+            text in "\\PC*",
+            nonce in "\\PC*",
+            tag in "\\PC*",
+            secret in "\\PC*",
+        ) {
+            let _ = decrypt(&text, &nonce, &tag, &secret);
+        }
+    }
 }
@@ -0,0 +1,52 @@
+//! Argon2id key derivation, as used by Infisical to protect the keys of encryption version 2 users
+
+use argon2::{Algorithm, Argon2, Params, Version};
+
+use crate::error::{self, Result};
+
+/// Memory cost in KiB used by Infisical
+const MEMORY_COST: u32 = 64 * 1024;
+/// Number of iterations used by Infisical
+const TIME_COST: u32 = 3;
+/// Degree of parallelism used by Infisical
+const PARALLELISM: u32 = 1;
+
+/// Derives a 32 byte key from a user's password and salt with the parameters Infisical uses
+pub fn derive_key(password: &str, salt: &str) -> Result<[u8; 32]> {
+    let params = Params::new(MEMORY_COST, TIME_COST, PARALLELISM, Some(32))
+        .map_err(|e| error::decode("argon2 parameters", e.to_string()))?;
+    let argon2 = Argon2::new(Algorithm::Argon2id, Version::V0x13, params);
+
+    let mut key = [0; 32];
+    argon2
+        .hash_password_into(password.as_bytes(), salt.as_bytes(), &mut key)
+        .map_err(|e| error::decode("salt", e.to_string()))?;
+
+    Ok(key)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::hex;
+
+    #[test]
+    fn derive_key_matches_reference() {
+        // Generated with the Argon2id implementation of the Python cryptography package
+        let key = derive_key(
+            "correct horse battery staple",
+            "4f7e1f0c9a2b3d5e6f708192a3b4c5d6",
+        )
+        .unwrap();
+
+        assert_eq!(
+            hex::encode(&key),
+            "6500af282d50f1e5a0f61a2487c08720320e94c13a93048994866db698c6599b"
+        );
+    }
+
+    #[test]
+    fn derive_key_rejects_short_salt() {
+        assert!(derive_key("password", "salt").unwrap_err().is_decode());
+    }
+}
@@ -1,9 +1,58 @@
 use base64::{engine::general_purpose, Engine as _};
 
+use crate::error::{self, Result};
+
 pub fn encode(str: &[u8]) -> String {
     general_purpose::STANDARD.encode(str)
 }
 
-pub fn decode(vec: &str) -> Vec<u8> {
-    general_purpose::STANDARD.decode(vec).unwrap()
+/// Decodes a standard base64 string
+///
+/// `field` names the value being decoded and is included in the error if it is not valid base64.
+pub fn decode(field: &'static str, value: &str) -> Result<Vec<u8>> {
+    general_purpose::STANDARD
+        .decode(value)
+        .map_err(|e| error::decode(field, e))
+}
+
+/// Decodes a standard base64 string that must contain exactly `N` bytes
+///
+/// `field` names the value being decoded and is included in the error if it is not valid base64
+/// or does not have the expected length.
+pub fn decode_array<const N: usize>(field: &'static str, value: &str) -> Result<[u8; N]> {
+    decode(field, value)?.try_into().map_err(|bytes: Vec<u8>| {
+        error::decode(field, format!("expected {} bytes, got {}", N, bytes.len()))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    proptest! {
+        #[test]
+        fn decode_inverts_encode(bytes in proptest::collection::vec(any::<u8>(), 0..256)) {
+            prop_assert_eq!(decode("value", &encode(&bytes)).unwrap(), bytes);
+        }
+
+        #[test]
+        fn decode_never_panics(value in "\\PC*") {
+            let _ = decode("value", &value);
+        }
+
+        #[test]
+        fn decode_array_rejects_wrong_lengths(bytes in proptest::collection::vec(any::<u8>(), 0..64)) {
+            let result = decode_array::<32>("key", &encode(&bytes));
+            prop_assert_eq!(result.is_ok(), bytes.len() == 32);
+        }
+    }
+
+    #[test]
+    fn decode_error_names_field() {
+        let err = decode("secretValueIV", "not base64!").unwrap_err();
+
+        assert!(err.is_decode());
+        assert!(err.to_string().contains("secretValueIV"));
+    }
 }
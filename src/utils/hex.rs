@@ -0,0 +1,53 @@
+//! Hexadecimal encoding, as used by Infisical for project keys and protected keys
+
+use crate::error::{self, Result};
+
+/// Encodes bytes as a lowercase hexadecimal string
+pub fn encode(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Decodes a hexadecimal string that must contain exactly `N` bytes
+///
+/// `field` names the value being decoded and is included in the error if it is not valid
+/// hexadecimal or does not have the expected length.
+pub fn decode_array<const N: usize>(field: &'static str, value: &str) -> Result<[u8; N]> {
+    if value.len() != N * 2 {
+        return Err(error::decode(
+            field,
+            format!(
+                "expected {} hexadecimal characters, got {}",
+                N * 2,
+                value.len()
+            ),
+        ));
+    }
+
+    let mut bytes = [0; N];
+    for (byte, pair) in bytes.iter_mut().zip(value.as_bytes().chunks(2)) {
+        *byte = std::str::from_utf8(pair)
+            .ok()
+            .and_then(|pair| u8::from_str_radix(pair, 16).ok())
+            .ok_or_else(|| error::decode(field, "expected only hexadecimal characters"))?;
+    }
+
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    proptest! {
+        #[test]
+        fn decode_array_inverts_encode(bytes in any::<[u8; 16]>()) {
+            prop_assert_eq!(decode_array::<16>("value", &encode(&bytes)).unwrap(), bytes);
+        }
+
+        #[test]
+        fn decode_array_never_panics(value in "\\PC*") {
+            let _ = decode_array::<16>("value", &value);
+        }
+    }
+}
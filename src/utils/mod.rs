@@ -1,3 +1,5 @@
 pub mod aes256gcm;
+pub mod argon2;
 pub mod base64;
-
+pub mod hex;
+pub mod nacl;
@@ -0,0 +1,182 @@
+//! Helpers for NaCl `crypto_box`, which Infisical uses to share project keys between users
+
+use onionsalt::crypto;
+use zeroize::Zeroize;
+
+use crate::error::{self, Result};
+use crate::keys::{PublicKey, UserPrivateKey};
+use crate::utils::base64;
+
+/// The number of zero bytes NaCl expects in front of a ciphertext and that it leaves in front of
+/// a decrypted message
+const BOX_ZEROBYTES: usize = 16;
+const BOX_BOXZEROBYTES: usize = 32;
+
+/// A value encrypted with NaCl's `crypto_box`, with its base64 encoded ciphertext and nonce
+#[derive(Debug)]
+pub struct Encryption {
+    pub ciphertext: String,
+    pub nonce: String,
+}
+
+/// Encrypts a value with NaCl's `crypto_box` using a random nonce
+///
+/// `public_key` is the public key of the receiver and `private_key` is the private key of the
+/// sender. This is the inverse of [`decrypt`].
+pub fn encrypt(message: &[u8], public_key: &PublicKey, private_key: &UserPrivateKey) -> Encryption {
+    let nonce = crypto::random_nonce();
+    let mut private_key = crypto::SecretKey(*private_key.as_bytes());
+
+    let mut padded_message = vec![0; BOX_BOXZEROBYTES];
+    padded_message.extend(message);
+
+    let mut ciphertext = vec![0; padded_message.len()];
+    crypto::box_up(
+        &mut ciphertext,
+        &padded_message,
+        &nonce,
+        &crypto::PublicKey(*public_key.as_bytes()),
+        &private_key,
+    );
+    private_key.0.zeroize();
+    padded_message.zeroize();
+
+    Encryption {
+        ciphertext: base64::encode(&ciphertext[BOX_ZEROBYTES..]),
+        nonce: base64::encode(&nonce.0),
+    }
+}
+
+/// Decrypts a value that was encrypted with NaCl's `crypto_box`, such as an Infisical project key
+///
+/// `ciphertext` and `nonce` are base64 encoded. `public_key` is the public key of the sender and
+/// `private_key` is the private key of the receiver.
+pub fn decrypt(
+    ciphertext: &str,
+    nonce: &str,
+    public_key: &PublicKey,
+    private_key: &UserPrivateKey,
+) -> Result<Vec<u8>> {
+    let ciphertext = base64::decode("ciphertext", ciphertext)?;
+    let nonce = base64::decode_array::<24>("nonce", nonce)?;
+    let mut private_key = crypto::SecretKey(*private_key.as_bytes());
+
+    // The ciphertext must at least contain the authentication tag
+    if ciphertext.len() < BOX_BOXZEROBYTES - BOX_ZEROBYTES {
+        return Err(error::decode(
+            "ciphertext",
+            format!(
+                "expected at least {} bytes, got {}",
+                BOX_BOXZEROBYTES - BOX_ZEROBYTES,
+                ciphertext.len()
+            ),
+        ));
+    }
+
+    let mut padded_ciphertext = vec![0; BOX_ZEROBYTES];
+    padded_ciphertext.extend(ciphertext);
+
+    let mut plaintext = vec![0; padded_ciphertext.len()];
+    let result = crypto::box_open(
+        &mut plaintext,
+        &padded_ciphertext,
+        &crypto::Nonce(nonce),
+        &crypto::PublicKey(*public_key.as_bytes()),
+        &private_key,
+    );
+    private_key.0.zeroize();
+    result?;
+    plaintext.drain(..BOX_BOXZEROBYTES);
+
+    Ok(plaintext)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    fn seal(
+        message: &[u8],
+        nonce: &[u8; 24],
+        sender: &crypto::KeyPair,
+        receiver: &crypto::KeyPair,
+    ) -> String {
+        let mut padded_message = vec![0; BOX_BOXZEROBYTES];
+        padded_message.extend(message);
+        let mut ciphertext = vec![0; padded_message.len()];
+        crypto::box_up(
+            &mut ciphertext,
+            &padded_message,
+            &crypto::Nonce(*nonce),
+            &receiver.public,
+            &sender.secret,
+        );
+
+        base64::encode(&ciphertext[BOX_ZEROBYTES..])
+    }
+
+    #[test]
+    fn decrypt_is_successful() {
+        let sender = crypto::box_keypair();
+        let receiver = crypto::box_keypair();
+        let nonce = crypto::random_24();
+        let ciphertext = seal(
+            b"0123456789abcdef0123456789abcdef",
+            &nonce,
+            &sender,
+            &receiver,
+        );
+
+        let plaintext = decrypt(
+            &ciphertext,
+            &base64::encode(&nonce),
+            &PublicKey::from_bytes(sender.public.0),
+            &UserPrivateKey::from_bytes(receiver.secret.0),
+        )
+        .unwrap();
+
+        assert_eq!(plaintext, b"0123456789abcdef0123456789abcdef");
+    }
+
+    #[test]
+    fn decrypt_inverts_encrypt() {
+        let sender = crypto::box_keypair();
+        let receiver = crypto::box_keypair();
+
+        let encryption = encrypt(
+            b"0123456789abcdef0123456789abcdef",
+            &PublicKey::from_bytes(receiver.public.0),
+            &UserPrivateKey::from_bytes(sender.secret.0),
+        );
+
+        let plaintext = decrypt(
+            &encryption.ciphertext,
+            &encryption.nonce,
+            &PublicKey::from_bytes(sender.public.0),
+            &UserPrivateKey::from_bytes(receiver.secret.0),
+        )
+        .unwrap();
+
+        assert_eq!(plaintext, b"0123456789abcdef0123456789abcdef");
+    }
+
+    proptest! {
+        #[test]
+        fn decrypt_never_panics(
+            ciphertext in proptest::collection::vec(any::<u8>(), 0..64),
+            nonce in proptest::collection::vec(any::<u8>(), 0..32),
+            public_key in any::<[u8; 32]>(),
+            private_key in any::<[u8; 32]>(),
+        ) {
+            let result = decrypt(
+                &base64::encode(&ciphertext),
+                &base64::encode(&nonce),
+                &PublicKey::from_bytes(public_key),
+                &UserPrivateKey::from_bytes(private_key),
+            );
+
+            prop_assert!(result.is_err());
+        }
+    }
+}
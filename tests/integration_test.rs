@@ -1,6 +1,6 @@
 use std::vec;
 
-use infisical_api::{api::models::SecretToCreate, utils::aes256gcm::encrypt};
+use infisical_api::api::models::{LogQuery, SecretToCreate};
 use tokio;
 mod common;
 
@@ -41,13 +41,13 @@ async fn add_secret() {
         .unwrap();
 
     let secret = SecretToCreate {
-        key: encrypt("EXTRA_SPECIAL_SECRET", &project_key)
+        key: project_key.encrypt("EXTRA_SPECIAL_SECRET").unwrap().into(),
+        value: project_key
+            .encrypt("THIS IS THE VALUE OF THE SECRET")
             .unwrap()
             .into(),
-        value: encrypt("THIS IS THE VALUE OF THE SECRET", &project_key)
-            .unwrap()
-            .into(),
-        comment: encrypt("This is my comment for the secret", &project_key)
+        comment: project_key
+            .encrypt("This is my comment for the secret")
             .unwrap()
             .into(),
         secret_type: "shared".to_string(),
@@ -114,7 +114,7 @@ async fn get_project_snapshots() {
 
     let client = infisical_api::Client::new(&env_vars.api_key).unwrap();
     let _snapshots = client
-        .get_project_snapshots(&env_vars.workspace_id, "0", "25")
+        .get_project_snapshots(&env_vars.workspace_id, 0, 25)
         .await
         .unwrap();
 }
@@ -124,7 +124,8 @@ async fn get_project_logs() {
     let env_vars = common::setup().unwrap();
 
     let client = infisical_api::Client::new(&env_vars.api_key).unwrap();
-    let _logs = client.get_project_logs(&env_vars.workspace_id, "", "0", "25", "", "");
+    let query = LogQuery::new().limit(25);
+    let _logs = client.get_project_logs(&env_vars.workspace_id, &query);
 }
 
 #[tokio::test]
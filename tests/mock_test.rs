@@ -0,0 +1,1782 @@
+use futures::{StreamExt, TryStreamExt};
+use infisical_api::api::models::{
+    Ability, DeniedPermission, LogAction, LogQuery, MembershipStatus, PermissionAction, Role,
+    RoleDefinition, RolePermission, SecretToCreate, SortOrder,
+};
+use infisical_api::audit::{ChangeValue, Redaction};
+use infisical_api::envelope;
+use infisical_api::error::Kind;
+use infisical_api::metrics::MetricsRecorder;
+use infisical_api::pagination::PageSize;
+use infisical_api::rbac;
+use infisical_api::reconcile;
+use infisical_api::rotation::{RotationCheckpoint, RotationProgress};
+use infisical_api::utils::nacl;
+use infisical_api::{ProjectKey, UserKeyPair};
+use serde_json::json;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use time::OffsetDateTime;
+use wiremock::matchers::{body_json, method, path, query_param};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+fn error_body(status_code: u16, message: &str) -> serde_json::Value {
+    json!({
+        "type": "not_found",
+        "message": message,
+        "context": {},
+        "level": 30,
+        "level_name": "INFO",
+        "status_code": status_code,
+        "datetime_iso": "2023-04-01T00:00:00.000Z",
+        "application": "infisical",
+        "extra": []
+    })
+}
+
+async fn client(server: &MockServer) -> infisical_api::Client {
+    infisical_api::ClientBuilder::new()
+        .api_base(&server.uri())
+        .build("test-api-key")
+        .unwrap()
+}
+
+#[tokio::test]
+async fn error_status_with_error_body_is_api_error() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/v2/users/me"))
+        .respond_with(ResponseTemplate::new(404).set_body_json(error_body(404, "User not found")))
+        .mount(&server)
+        .await;
+
+    let err = client(&server).await.get_user().await.unwrap_err();
+
+    assert!(err.is_api());
+    assert_eq!(err.status(), Some(reqwest::StatusCode::NOT_FOUND));
+    assert_eq!(err.api_error().unwrap().message, "User not found");
+}
+
+#[tokio::test]
+async fn ok_status_with_error_body_is_api_error() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/v2/users/me"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(error_body(401, "Unauthorized")))
+        .mount(&server)
+        .await;
+
+    let err = client(&server).await.get_user().await.unwrap_err();
+
+    assert!(err.is_api());
+    assert_eq!(err.status(), Some(reqwest::StatusCode::UNAUTHORIZED));
+}
+
+#[tokio::test]
+async fn error_status_without_error_body_keeps_raw_body() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/v2/users/me"))
+        .respond_with(ResponseTemplate::new(502).set_body_string("<html>Bad Gateway</html>"))
+        .mount(&server)
+        .await;
+
+    let err = client(&server).await.get_user().await.unwrap_err();
+
+    assert_eq!(err.kind(), &Kind::UnexpectedResponse);
+    assert_eq!(err.status(), Some(reqwest::StatusCode::BAD_GATEWAY));
+    assert_eq!(err.response_body(), Some("<html>Bad Gateway</html>"));
+    assert!(err.is_retryable());
+}
+
+#[tokio::test]
+async fn unexpected_response_shape_keeps_raw_body() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/v2/users/me/organizations"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(r#"{"orgs":[]}"#))
+        .mount(&server)
+        .await;
+
+    let err = client(&server)
+        .await
+        .get_my_organizations()
+        .await
+        .err()
+        .unwrap();
+
+    assert_eq!(err.kind(), &Kind::UnexpectedResponse);
+    assert_eq!(err.status(), Some(reqwest::StatusCode::OK));
+    assert_eq!(err.response_body(), Some(r#"{"orgs":[]}"#));
+    assert!(!err.is_retryable());
+}
+
+#[derive(Clone, Default)]
+struct CountingRecorder {
+    requests: Arc<Mutex<Vec<(&'static str, Option<&'static str>)>>>,
+    decryption_failures: Arc<Mutex<Vec<&'static str>>>,
+    cache_lookups: Arc<Mutex<Vec<bool>>>,
+}
+
+impl MetricsRecorder for CountingRecorder {
+    fn record_request(
+        &self,
+        endpoint: &'static str,
+        _latency: Duration,
+        error: Option<&'static str>,
+    ) {
+        self.requests.lock().unwrap().push((endpoint, error));
+    }
+
+    fn record_decryption_failure(&self, error: &'static str) {
+        self.decryption_failures.lock().unwrap().push(error);
+    }
+
+    fn record_cache(&self, hit: bool) {
+        self.cache_lookups.lock().unwrap().push(hit);
+    }
+}
+
+#[tokio::test]
+async fn metrics_recorder_counts_requests_and_decryption_failures() {
+    let server = MockServer::start().await;
+    let recorder = CountingRecorder::default();
+    let client = infisical_api::ClientBuilder::new()
+        .api_base(&server.uri())
+        .metrics(recorder.clone())
+        .build("test-api-key")
+        .unwrap();
+
+    Mock::given(method("GET"))
+        .and(path("/v2/users/me"))
+        .respond_with(ResponseTemplate::new(404).set_body_json(error_body(404, "User not found")))
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/v2/workspace/workspace-id/encrypted-key"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "encryptedKey": "a2V5",
+            "nonce": "bm9uY2U=",
+            "sender": { "publicKey": "not a public key" },
+            "receiver": "user-id",
+            "workspace": "workspace-id",
+        })))
+        .mount(&server)
+        .await;
+
+    client.get_user().await.unwrap_err();
+    let err = client
+        .get_decrypted_project_key("workspace-id", &UserKeyPair::generate().private_key)
+        .await
+        .unwrap_err();
+
+    assert!(err.is_decode());
+    assert_eq!(
+        *recorder.requests.lock().unwrap(),
+        vec![("get_my_user", Some("api")), ("get_project_key", None)]
+    );
+    assert_eq!(
+        *recorder.decryption_failures.lock().unwrap(),
+        vec!["decode"]
+    );
+}
+
+#[tokio::test]
+async fn add_project_member_shares_project_key() {
+    let server = MockServer::start().await;
+    let me = UserKeyPair::generate();
+    let invitee = UserKeyPair::generate();
+    let project_key = ProjectKey::from_bytes(&[0xab; 16]);
+
+    Mock::given(method("POST"))
+        .and(path("/v1/workspace/workspace-id/invite-signup"))
+        .and(body_json(json!({ "email": "jane@example.com" })))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "invitee": {
+                "_id": "user-id",
+                "email": "jane@example.com",
+                "publicKey": invitee.public_key.to_base64(),
+            },
+            "latestKey": null,
+        })))
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/v1/key/workspace-id"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .set_body_json(json!({ "message": "Successfully uploaded key to workspace" })),
+        )
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let added = client(&server)
+        .await
+        .add_project_member(
+            "workspace-id",
+            "jane@example.com",
+            &project_key,
+            &me.private_key,
+        )
+        .await
+        .unwrap();
+    assert_eq!(added.id, "user-id");
+
+    let requests = server.received_requests().await.unwrap();
+    let upload: serde_json::Value = requests[1].body_json().unwrap();
+    assert_eq!(upload["key"]["userId"], "user-id");
+
+    let shared_key = nacl::decrypt(
+        upload["key"]["encryptedKey"].as_str().unwrap(),
+        upload["key"]["nonce"].as_str().unwrap(),
+        &me.public_key,
+        &invitee.private_key,
+    )
+    .unwrap();
+    assert_eq!(shared_key, project_key.expose().as_bytes());
+}
+
+#[tokio::test]
+async fn add_project_member_by_user_id_looks_up_email() {
+    let server = MockServer::start().await;
+    let me = UserKeyPair::generate();
+    let invitee = UserKeyPair::generate();
+
+    Mock::given(method("GET"))
+        .and(path("/v1/workspace/workspace-id"))
+        .respond_with(
+            ResponseTemplate::new(200).set_body_json(json!({ "workspace": workspace("Project") })),
+        )
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/v2/organizations/organization-id/memberships"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "memberships": [organization_membership("member")],
+        })))
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/v1/workspace/workspace-id/invite-signup"))
+        .and(body_json(json!({ "email": "jane@example.com" })))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "invitee": {
+                "_id": "user-id",
+                "email": "jane@example.com",
+                "publicKey": invitee.public_key.to_base64(),
+            },
+        })))
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/v1/key/workspace-id"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .set_body_json(json!({ "message": "Successfully uploaded key to workspace" })),
+        )
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let client = client(&server).await;
+    let project_key = ProjectKey::generate();
+    let added = client
+        .add_project_member_by_user_id("workspace-id", "user-id", &project_key, &me.private_key)
+        .await
+        .unwrap();
+    assert_eq!(added.email, "jane@example.com");
+
+    let err = client
+        .add_project_member_by_user_id("workspace-id", "unknown", &project_key, &me.private_key)
+        .await
+        .unwrap_err();
+    assert!(err.is_not_found());
+}
+
+#[tokio::test]
+async fn add_project_member_requires_public_key() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/v1/workspace/workspace-id/invite-signup"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "invitee": { "_id": "user-id", "email": "jane@example.com" },
+        })))
+        .mount(&server)
+        .await;
+
+    let me = UserKeyPair::generate();
+    let err = client(&server)
+        .await
+        .add_project_member(
+            "workspace-id",
+            "jane@example.com",
+            &ProjectKey::from_bytes(&[0xab; 16]),
+            &me.private_key,
+        )
+        .await
+        .unwrap_err();
+
+    assert!(err.is_not_found());
+}
+
+fn encrypted_secret(
+    id: &str,
+    key: &str,
+    value: &str,
+    project_key: &ProjectKey,
+) -> serde_json::Value {
+    let key = project_key.encrypt(key).unwrap();
+    let value = project_key.encrypt(value).unwrap();
+
+    json!({
+        "_id": id,
+        "version": 1,
+        "workspace": "workspace-id",
+        "type": "shared",
+        "secretKeyCiphertext": key.text,
+        "secretKeyIV": key.nonce,
+        "secretKeyTag": key.tag,
+        "secretValueCiphertext": value.text,
+        "secretValueIV": value.nonce,
+        "secretValueTag": value.tag,
+        "createdAt": "2023-04-01T00:00:00.000Z",
+        "updatedAt": "2023-04-01T00:00:00.000Z",
+    })
+}
+
+fn project_membership(user_id: &str, public_key: Option<String>) -> serde_json::Value {
+    json!({
+        "_id": format!("membership-{}", user_id),
+        "role": "admin",
+        "workspace": "workspace-id",
+        "deniedPermissions": [],
+        "user": {
+            "_id": user_id,
+            "email": format!("{}@example.com", user_id),
+            "firstName": "Jane",
+            "lastName": "Doe",
+            "publicKey": public_key,
+            "__v": 0,
+            "devices": [],
+            "encryptionVersion": 2,
+            "isMfaEnabled": false,
+            "mfaMethods": [],
+            "createdAt": "2023-04-01T00:00:00.000Z",
+            "updatedAt": "2023-04-01T00:00:00.000Z",
+        },
+        "createdAt": "2023-04-01T00:00:00.000Z",
+        "updatedAt": "2023-04-01T00:00:00.000Z",
+    })
+}
+
+async fn mount_rotation_project(server: &MockServer, me: &UserKeyPair, old_key: &ProjectKey) {
+    Mock::given(method("GET"))
+        .and(path("/v1/workspace/workspace-id"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "workspace": {
+                "_id": "workspace-id",
+                "name": "Project",
+                "organization": "organization-id",
+                "environments": [
+                    { "name": "Development", "slug": "dev" },
+                    { "name": "Production", "slug": "prod" },
+                ],
+            },
+        })))
+        .mount(server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/v2/secrets"))
+        .and(query_param("environment", "dev"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "secrets": [encrypted_secret("secret-id", "DATABASE_URL", "postgres://", old_key)],
+        })))
+        .mount(server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/v2/secrets"))
+        .and(query_param("environment", "prod"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "secrets": [] })))
+        .mount(server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/v2/workspace/workspace-id/memberships"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "memberships": [
+                project_membership("me", Some(me.public_key.to_base64())),
+                project_membership("pending", None),
+                project_membership("broken", Some("not a public key".to_string())),
+            ],
+        })))
+        .mount(server)
+        .await;
+}
+
+#[tokio::test]
+async fn rotate_project_key_reencrypts_secrets_and_shares_key() {
+    let server = MockServer::start().await;
+    let me = UserKeyPair::generate();
+    let old_key = ProjectKey::generate();
+    mount_rotation_project(&server, &me, &old_key).await;
+    Mock::given(method("PATCH"))
+        .and(path("/v2/secrets"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "secrets": [] })))
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/v1/key/workspace-id"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .set_body_json(json!({ "message": "Successfully uploaded key to workspace" })),
+        )
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let mut checkpoint = RotationCheckpoint::new();
+    let mut progress = Vec::new();
+    let report = client(&server)
+        .await
+        .rotate_project_key(
+            "workspace-id",
+            &old_key,
+            &me.private_key,
+            &mut checkpoint,
+            false,
+            |step| progress.push(step),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(report.reencrypted_secrets, 1);
+    assert_eq!(report.shared_with, ["me"]);
+    assert_eq!(report.members_without_public_key, ["pending"]);
+    assert_eq!(report.members_with_invalid_public_key, ["broken"]);
+    assert_eq!(checkpoint.completed_environments(), ["dev", "prod"]);
+    assert_eq!(checkpoint.shared_with(), ["me"]);
+    assert_eq!(
+        progress[0],
+        RotationProgress::EnvironmentReencrypted {
+            environment: "dev".to_string(),
+            secrets: 1
+        }
+    );
+
+    let requests = server.received_requests().await.unwrap();
+    let update: serde_json::Value = requests
+        .iter()
+        .find(|request| request.method == wiremock::http::Method::Patch)
+        .unwrap()
+        .body_json()
+        .unwrap();
+    let secret = &update["secrets"][0];
+    assert_eq!(secret["id"], "secret-id");
+    let value = checkpoint
+        .new_key()
+        .decrypt(
+            secret["secretValueCiphertext"].as_str().unwrap(),
+            secret["secretValueIV"].as_str().unwrap(),
+            secret["secretValueTag"].as_str().unwrap(),
+        )
+        .unwrap();
+    assert_eq!(value, "postgres://");
+
+    let upload: serde_json::Value = requests
+        .iter()
+        .find(|request| request.url.path() == "/v1/key/workspace-id")
+        .unwrap()
+        .body_json()
+        .unwrap();
+    let shared_key = nacl::decrypt(
+        upload["key"]["encryptedKey"].as_str().unwrap(),
+        upload["key"]["nonce"].as_str().unwrap(),
+        &me.public_key,
+        &me.private_key,
+    )
+    .unwrap();
+    assert_eq!(shared_key, checkpoint.new_key().expose().as_bytes());
+}
+
+#[tokio::test]
+async fn rotate_project_key_dry_run_uploads_nothing() {
+    let server = MockServer::start().await;
+    let me = UserKeyPair::generate();
+    let old_key = ProjectKey::generate();
+    mount_rotation_project(&server, &me, &old_key).await;
+    Mock::given(method("PATCH"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&server)
+        .await;
+
+    let mut checkpoint = RotationCheckpoint::new();
+    let report = client(&server)
+        .await
+        .rotate_project_key(
+            "workspace-id",
+            &old_key,
+            &me.private_key,
+            &mut checkpoint,
+            true,
+            |_| {},
+        )
+        .await
+        .unwrap();
+
+    assert!(report.dry_run);
+    assert_eq!(report.reencrypted_secrets, 1);
+    assert_eq!(report.shared_with, ["me"]);
+    assert!(checkpoint.completed_environments().is_empty());
+    assert!(checkpoint.shared_with().is_empty());
+}
+
+#[tokio::test]
+async fn rotate_project_key_resumes_after_unsaved_checkpoint() {
+    let server = MockServer::start().await;
+    let me = UserKeyPair::generate();
+    // The secrets were uploaded with the new key, but the checkpoint wasn't saved afterwards
+    let mut checkpoint = RotationCheckpoint::resume(ProjectKey::generate(), Vec::new(), Vec::new());
+    mount_rotation_project(&server, &me, checkpoint.new_key()).await;
+    Mock::given(method("PATCH"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/v1/key/workspace-id"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .set_body_json(json!({ "message": "Successfully uploaded key to workspace" })),
+        )
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let report = client(&server)
+        .await
+        .rotate_project_key(
+            "workspace-id",
+            &ProjectKey::generate(),
+            &me.private_key,
+            &mut checkpoint,
+            false,
+            |_| {},
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(report.reencrypted_secrets, 0);
+    assert_eq!(report.already_reencrypted_secrets, 1);
+    assert_eq!(checkpoint.completed_environments(), ["dev", "prod"]);
+    assert_eq!(checkpoint.shared_with(), ["me"]);
+}
+
+#[tokio::test]
+async fn rotate_project_key_resumes_from_checkpoint() {
+    let server = MockServer::start().await;
+    let me = UserKeyPair::generate();
+    // Secrets in completed environments are already encrypted with the new key, so decrypting
+    // them with the old key would fail if they weren't skipped
+    let mut checkpoint = RotationCheckpoint::resume(
+        ProjectKey::generate(),
+        vec!["dev".to_string(), "prod".to_string()],
+        Vec::new(),
+    );
+    mount_rotation_project(&server, &me, checkpoint.new_key()).await;
+    Mock::given(method("POST"))
+        .and(path("/v1/key/workspace-id"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .set_body_json(json!({ "message": "Successfully uploaded key to workspace" })),
+        )
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let mut progress = Vec::new();
+    let report = client(&server)
+        .await
+        .rotate_project_key(
+            "workspace-id",
+            &ProjectKey::generate(),
+            &me.private_key,
+            &mut checkpoint,
+            false,
+            |step| progress.push(step),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(report.reencrypted_secrets, 0);
+    assert_eq!(
+        progress[0],
+        RotationProgress::EnvironmentSkipped {
+            environment: "dev".to_string()
+        }
+    );
+    assert_eq!(checkpoint.shared_with(), ["me"]);
+}
+
+#[tokio::test]
+async fn get_envelope_secret_reassembles_chunks() {
+    let server = MockServer::start().await;
+    let project_key = ProjectKey::generate();
+    let value: Vec<u8> = (0..=255).collect();
+    let sealed = envelope::seal("KEYSTORE", &value, 100).unwrap();
+
+    // Neither an unrelated secret nor one that doesn't decrypt keep the envelope from being read
+    let mut secrets = vec![
+        encrypted_secret("other", "OTHER", "value", &project_key),
+        encrypted_secret("corrupted", "CORRUPTED", "value", &ProjectKey::generate()),
+        encrypted_secret(
+            "manifest",
+            "KEYSTORE",
+            &sealed.manifest.to_value(),
+            &project_key,
+        ),
+    ];
+    for (key, value) in &sealed.chunks {
+        secrets.push(encrypted_secret(key, key, value.expose(), &project_key));
+    }
+    Mock::given(method("GET"))
+        .and(path("/v2/secrets"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "secrets": secrets })))
+        .mount(&server)
+        .await;
+
+    let opened = client(&server)
+        .await
+        .get_envelope_secret("workspace-id", "dev", "KEYSTORE", &project_key)
+        .await
+        .unwrap();
+
+    assert_eq!(opened.expose(), value);
+}
+
+fn workspace(name: &str) -> serde_json::Value {
+    json!({
+        "_id": "workspace-id",
+        "name": name,
+        "organization": "organization-id",
+        "environments": [{ "name": "Development", "slug": "dev" }],
+    })
+}
+
+#[tokio::test]
+async fn create_project_uploads_new_project_key() {
+    let server = MockServer::start().await;
+    let me = UserKeyPair::generate();
+    let encrypted = me.encrypt("hunter2").unwrap();
+
+    Mock::given(method("POST"))
+        .and(path("/v1/workspace"))
+        .and(body_json(json!({
+            "workspaceName": "Project",
+            "organizationId": "organization-id",
+        })))
+        .respond_with(
+            ResponseTemplate::new(200).set_body_json(json!({ "workspace": workspace("Project") })),
+        )
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/v2/users/me"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "user": {
+                "_id": "me",
+                "email": "me@example.com",
+                "firstName": "Jane",
+                "lastName": "Doe",
+                "publicKey": encrypted.public_key,
+                "encryptedPrivateKey": encrypted.encrypted_private_key,
+                "iv": encrypted.iv,
+                "tag": encrypted.tag,
+                "salt": encrypted.salt,
+                "__v": 0,
+                "devices": [],
+                "encryptionVersion": 1,
+                "isMfaEnabled": false,
+                "mfaMethods": [],
+                "createdAt": "2023-04-01T00:00:00.000Z",
+                "updatedAt": "2023-04-01T00:00:00.000Z",
+            },
+        })))
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/v1/key/workspace-id"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "message": "Successfully uploaded key to workspace",
+            "key": { "workspace": "workspace-id" },
+        })))
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("DELETE"))
+        .and(path("/v1/workspace/workspace-id"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&server)
+        .await;
+
+    let (workspace, project_key) = client(&server)
+        .await
+        .create_project("organization-id", "Project", &me.private_key)
+        .await
+        .unwrap();
+    assert_eq!(workspace.id, "workspace-id");
+
+    let requests = server.received_requests().await.unwrap();
+    let upload: serde_json::Value = requests
+        .iter()
+        .find(|request| request.url.path() == "/v1/key/workspace-id")
+        .unwrap()
+        .body_json()
+        .unwrap();
+    assert_eq!(upload["key"]["userId"], "me");
+
+    let shared_key = nacl::decrypt(
+        upload["key"]["encryptedKey"].as_str().unwrap(),
+        upload["key"]["nonce"].as_str().unwrap(),
+        &me.public_key,
+        &me.private_key,
+    )
+    .unwrap();
+    assert_eq!(shared_key, project_key.expose().as_bytes());
+}
+
+#[tokio::test]
+async fn create_project_deletes_project_when_key_upload_fails() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/v1/workspace"))
+        .respond_with(
+            ResponseTemplate::new(200).set_body_json(json!({ "workspace": workspace("Project") })),
+        )
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/v2/users/me"))
+        .respond_with(ResponseTemplate::new(503).set_body_json(error_body(503, "Unavailable")))
+        .mount(&server)
+        .await;
+    Mock::given(method("DELETE"))
+        .and(path("/v1/workspace/workspace-id"))
+        .respond_with(
+            ResponseTemplate::new(200).set_body_json(json!({ "workspace": workspace("Project") })),
+        )
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let err = client(&server)
+        .await
+        .create_project(
+            "organization-id",
+            "Project",
+            &UserKeyPair::generate().private_key,
+        )
+        .await
+        .err()
+        .unwrap();
+
+    assert_eq!(err.status(), Some(reqwest::StatusCode::SERVICE_UNAVAILABLE));
+}
+
+#[tokio::test]
+async fn rename_and_delete_project() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/v1/workspace/workspace-id/name"))
+        .and(body_json(json!({ "name": "Renamed" })))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "message": "Successfully changed workspace settings",
+            "workspace": workspace("Renamed"),
+        })))
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("DELETE"))
+        .and(path("/v1/workspace/workspace-id"))
+        .respond_with(
+            ResponseTemplate::new(200).set_body_json(json!({ "workspace": workspace("Renamed") })),
+        )
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let client = client(&server).await;
+    let renamed = client
+        .rename_project("workspace-id", "Renamed")
+        .await
+        .unwrap();
+    let deleted = client.delete_project("workspace-id").await.unwrap();
+
+    assert_eq!(renamed.name, "Renamed");
+    assert_eq!(deleted.id, "workspace-id");
+}
+
+#[tokio::test]
+async fn create_environment_validates_slug_locally() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&server)
+        .await;
+
+    let err = client(&server)
+        .await
+        .create_environment("workspace-id", "Preview", "Preview Env")
+        .await
+        .unwrap_err();
+
+    assert!(err.is_invalid_input());
+}
+
+#[tokio::test]
+async fn manage_environments() {
+    let server = MockServer::start().await;
+    let environment = |name: &str, slug: &str| {
+        json!({
+            "message": "Successfully updated environment",
+            "workspace": "workspace-id",
+            "environment": { "name": name, "slug": slug },
+        })
+    };
+    Mock::given(method("POST"))
+        .and(path("/v2/workspace/workspace-id/environments"))
+        .and(body_json(json!({
+            "environmentName": "Preview",
+            "environmentSlug": "preview",
+        })))
+        .respond_with(ResponseTemplate::new(200).set_body_json(environment("Preview", "preview")))
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("PUT"))
+        .and(path("/v2/workspace/workspace-id/environments"))
+        .and(body_json(json!({
+            "oldEnvironmentSlug": "preview",
+            "environmentName": "Review",
+            "environmentSlug": "review",
+        })))
+        .respond_with(ResponseTemplate::new(200).set_body_json(environment("Review", "review")))
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("DELETE"))
+        .and(path("/v2/workspace/workspace-id/environments"))
+        .and(body_json(json!({ "environmentSlug": "review" })))
+        .respond_with(ResponseTemplate::new(200).set_body_json(environment("Review", "review")))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let client = client(&server).await;
+    let created = client
+        .create_environment("workspace-id", "Preview", "preview")
+        .await
+        .unwrap();
+    let renamed = client
+        .rename_environment("workspace-id", "preview", "Review", "review")
+        .await
+        .unwrap();
+    let deleted = client
+        .delete_environment("workspace-id", "review")
+        .await
+        .unwrap();
+
+    assert_eq!(created.slug, "preview");
+    assert_eq!(renamed.name, "Review");
+    assert_eq!(deleted.slug, "review");
+}
+
+#[tokio::test]
+async fn reorder_environments_swaps_out_of_place_environments() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/v1/workspace/workspace-id"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "workspace": {
+                "_id": "workspace-id",
+                "name": "Project",
+                "organization": "organization-id",
+                "environments": [
+                    { "name": "Production", "slug": "prod" },
+                    { "name": "Development", "slug": "dev" },
+                    { "name": "Staging", "slug": "staging" },
+                ],
+            },
+        })))
+        .mount(&server)
+        .await;
+    Mock::given(method("PATCH"))
+        .and(path("/v2/workspace/workspace-id/environments"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .set_body_json(json!({ "message": "Successfully reordered environments" })),
+        )
+        .expect(2)
+        .mount(&server)
+        .await;
+
+    let client = client(&server).await;
+    let environments = client
+        .reorder_environments("workspace-id", &["dev", "staging", "prod"])
+        .await
+        .unwrap();
+
+    let slugs: Vec<_> = environments.iter().map(|e| e.slug.as_str()).collect();
+    assert_eq!(slugs, ["dev", "staging", "prod"]);
+
+    let requests = server.received_requests().await.unwrap();
+    let first_swap: serde_json::Value = requests[1].body_json().unwrap();
+    assert_eq!(
+        first_swap,
+        json!({
+            "environmentSlug": "prod",
+            "environmentName": "Production",
+            "otherEnvironmentSlug": "dev",
+            "otherEnvironmentName": "Development",
+        })
+    );
+
+    let err = client
+        .reorder_environments("workspace-id", &["dev", "prod"])
+        .await
+        .unwrap_err();
+    assert!(err.is_invalid_input());
+}
+
+#[tokio::test]
+async fn plan_and_apply_spec() {
+    let server = MockServer::start().await;
+    let me = UserKeyPair::generate();
+    let encrypted = me.encrypt("hunter2").unwrap();
+    let project_key = ProjectKey::generate();
+    let shared_key = nacl::encrypt(
+        project_key.expose().as_bytes(),
+        &me.public_key,
+        &me.private_key,
+    );
+
+    Mock::given(method("GET"))
+        .and(path("/v2/users/me"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "user": {
+                "_id": "me",
+                "email": "me@example.com",
+                "firstName": "Jane",
+                "lastName": "Doe",
+                "publicKey": encrypted.public_key,
+                "encryptedPrivateKey": encrypted.encrypted_private_key,
+                "iv": encrypted.iv,
+                "tag": encrypted.tag,
+                "salt": encrypted.salt,
+                "__v": 0,
+                "devices": [],
+                "encryptionVersion": 1,
+                "isMfaEnabled": false,
+                "mfaMethods": [],
+                "createdAt": "2023-04-01T00:00:00.000Z",
+                "updatedAt": "2023-04-01T00:00:00.000Z",
+            },
+        })))
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/v2/organizations/organization-id/workspaces"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .set_body_json(json!({ "workspaces": [workspace("Project")] })),
+        )
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/v2/workspace/workspace-id/memberships"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "memberships": [
+                project_membership("me", Some(me.public_key.to_base64())),
+                project_membership("bob", None),
+            ],
+        })))
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/v2/workspace/workspace-id/encrypted-key"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "encryptedKey": shared_key.ciphertext,
+            "nonce": shared_key.nonce,
+            "sender": { "publicKey": me.public_key.to_base64() },
+            "receiver": "me",
+            "workspace": "workspace-id",
+        })))
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/v2/secrets"))
+        .and(query_param("environment", "dev"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "secrets": [
+                encrypted_secret("secret-id", "DATABASE_URL", "postgres://", &project_key),
+                encrypted_secret("old-id", "OLD_KEY", "value", &project_key),
+            ],
+        })))
+        .mount(&server)
+        .await;
+
+    let spec = reconcile::Spec {
+        organization: "organization-id".to_string(),
+        projects: vec![reconcile::ProjectSpec {
+            name: "Project".to_string(),
+            environments: Some(vec![
+                reconcile::EnvironmentSpec {
+                    name: "Development".to_string(),
+                    slug: "dev".to_string(),
+                },
+                reconcile::EnvironmentSpec {
+                    name: "Production".to_string(),
+                    slug: "prod".to_string(),
+                },
+            ]),
+            members: Some(vec![reconcile::MemberSpec {
+                email: "me@example.com".to_string(),
+                role: Role::Admin,
+            }]),
+            secrets: Some(
+                [(
+                    "dev".to_string(),
+                    vec![
+                        "DATABASE_URL".to_string(),
+                        "API_KEY".to_string(),
+                        "API_SECRET".to_string(),
+                    ],
+                )]
+                .into_iter()
+                .collect(),
+            ),
+        }],
+    };
+
+    let recorder = CountingRecorder::default();
+    let client = infisical_api::ClientBuilder::new()
+        .api_base(&server.uri())
+        .metrics(recorder.clone())
+        .build("test-api-key")
+        .unwrap();
+    let plan = client.plan(&spec, &me.private_key).await.unwrap();
+
+    assert_eq!(plan.projects[0].id.as_deref(), Some("workspace-id"));
+    assert_eq!(
+        serde_json::to_value(&plan.projects[0].changes).unwrap(),
+        json!([
+            { "action": "create_environment", "name": "Production", "slug": "prod" },
+            { "action": "create_secret", "environment": "dev", "key": "API_KEY" },
+            { "action": "create_secret", "environment": "dev", "key": "API_SECRET" },
+            { "action": "delete_secret", "environment": "dev", "key": "OLD_KEY", "id": "old-id" },
+            { "action": "remove_member", "email": "bob@example.com", "membership_id": "membership-bob" },
+        ])
+    );
+
+    Mock::given(method("POST"))
+        .and(path("/v2/workspace/workspace-id/environments"))
+        .and(body_json(json!({
+            "environmentName": "Production",
+            "environmentSlug": "prod",
+        })))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "message": "Successfully created new environment",
+            "workspace": "workspace-id",
+            "environment": { "name": "Production", "slug": "prod" },
+        })))
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/v2/secrets"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "secrets": [] })))
+        .expect(2)
+        .mount(&server)
+        .await;
+    Mock::given(method("DELETE"))
+        .and(path("/v2/secrets"))
+        .and(body_json(json!({ "secretIds": ["old-id"] })))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "secrets": [] })))
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("DELETE"))
+        .and(path(
+            "/v2/workspace/workspace-id/memberships/membership-bob",
+        ))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "membership": project_membership("bob", None),
+        })))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    client.apply(&spec, &plan, &me.private_key).await.unwrap();
+
+    let requests = server.received_requests().await.unwrap();
+    let created: serde_json::Value = requests
+        .iter()
+        .find(|request| {
+            request.method == wiremock::http::Method::Post && request.url.path() == "/v2/secrets"
+        })
+        .unwrap()
+        .body_json()
+        .unwrap();
+    let key = &created["secrets"][0];
+    assert_eq!(
+        project_key
+            .decrypt(
+                key["secretKeyCiphertext"].as_str().unwrap(),
+                key["secretKeyIV"].as_str().unwrap(),
+                key["secretKeyTag"].as_str().unwrap(),
+            )
+            .unwrap(),
+        "API_KEY"
+    );
+    assert_eq!(*recorder.cache_lookups.lock().unwrap(), vec![false, true]);
+}
+
+fn organization_membership(role: &str) -> serde_json::Value {
+    json!({
+        "_id": "membership-id",
+        "organization": "organization-id",
+        "role": role,
+        "status": "accepted",
+        "user": {
+            "_id": "user-id",
+            "email": "jane@example.com",
+            "firstName": "Jane",
+            "lastName": "Doe",
+            "__v": 0,
+            "devices": [],
+            "encryptionVersion": 2,
+            "isMfaEnabled": false,
+            "mfaMethods": [],
+            "createdAt": "2023-04-01T00:00:00.000Z",
+            "updatedAt": "2023-04-01T00:00:00.000Z",
+        },
+        "createdAt": "2023-04-01T00:00:00.000Z",
+        "updatedAt": "2023-04-01T00:00:00.000Z",
+    })
+}
+
+#[tokio::test]
+async fn membership_updates_send_json_role_and_deletes_send_no_body() {
+    let server = MockServer::start().await;
+    Mock::given(method("PATCH"))
+        .and(path(
+            "/v2/organizations/organization-id/memberships/membership-id",
+        ))
+        .and(body_json(json!({ "role": "admin" })))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "membership": organization_membership("admin"),
+        })))
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("PATCH"))
+        .and(path("/v2/workspace/workspace-id/memberships/membership-me"))
+        .and(body_json(json!({ "role": "viewer" })))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "membership": project_membership("me", None),
+        })))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    Mock::given(method("DELETE"))
+        .and(path(
+            "/v2/organizations/organization-id/memberships/membership-id",
+        ))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "membership": organization_membership("admin"),
+        })))
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("DELETE"))
+        .and(path("/v2/workspace/workspace-id/memberships/membership-me"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "membership": project_membership("me", None),
+        })))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let client = client(&server).await;
+    let organization_membership = client
+        .update_organization_membership("organization-id", "membership-id", Role::Admin)
+        .await
+        .unwrap();
+    client
+        .update_project_membership("workspace-id", "membership-me", Role::Viewer)
+        .await
+        .unwrap();
+
+    client
+        .delete_organization_membership("organization-id", "membership-id")
+        .await
+        .unwrap();
+    client
+        .delete_project_membership("workspace-id", "membership-me")
+        .await
+        .unwrap();
+
+    assert_eq!(organization_membership.role, Role::Admin);
+    assert_eq!(organization_membership.status, MembershipStatus::Accepted);
+
+    let requests = server.received_requests().await.unwrap();
+    assert!(requests
+        .iter()
+        .filter(|request| request.method == wiremock::http::Method::Delete)
+        .all(|request| request.body.is_empty()));
+}
+
+#[tokio::test]
+async fn roll_back_to_snapshot_sends_json_version() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/v1/workspace/workspace-id/secret-snapshots/rollback"))
+        .and(body_json(json!({ "version": 3 })))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "secrets": [] })))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let secrets = client(&server)
+        .await
+        .roll_back_to_snapshot("workspace-id", 3)
+        .await
+        .unwrap();
+
+    assert!(secrets.is_empty());
+}
+
+#[tokio::test]
+async fn create_project_secrets_sends_json_body() {
+    let server = MockServer::start().await;
+    let project_key = ProjectKey::generate();
+    Mock::given(method("POST"))
+        .and(path("/v2/secrets"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "secrets": [encrypted_secret("secret-id", "API_KEY", "value", &project_key)],
+        })))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let encrypt = |text| project_key.encrypt(text).unwrap();
+    let secret = SecretToCreate {
+        secret_type: "shared".to_string(),
+        key: encrypt("API_KEY").into(),
+        value: encrypt("value").into(),
+        comment: encrypt("").into(),
+    };
+    client(&server)
+        .await
+        .create_project_secrets("workspace-id", "dev", vec![secret])
+        .await
+        .unwrap();
+
+    let requests = server.received_requests().await.unwrap();
+    let body: serde_json::Value = requests[0].body_json().unwrap();
+    let mut fields: Vec<_> = body.as_object().unwrap().keys().collect();
+    fields.sort();
+    assert_eq!(fields, ["environment", "secrets", "workspaceId"]);
+
+    let mut secret_fields: Vec<_> = body["secrets"][0].as_object().unwrap().keys().collect();
+    secret_fields.sort();
+    assert_eq!(
+        secret_fields,
+        [
+            "secretCommentCiphertext",
+            "secretCommentIV",
+            "secretCommentTag",
+            "secretKeyCiphertext",
+            "secretKeyIV",
+            "secretKeyTag",
+            "secretValueCiphertext",
+            "secretValueIV",
+            "secretValueTag",
+            "type",
+        ]
+    );
+}
+
+#[tokio::test]
+async fn manage_organization_invitations() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/v2/organizations/organization-id/memberships"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "memberships": [
+                organization_membership("admin"),
+                {
+                    "_id": "invitation-id",
+                    "organization": "organization-id",
+                    "role": "member",
+                    "status": "invited",
+                    "inviteEmail": "john@example.com",
+                    "createdAt": "2023-04-01T00:00:00.000Z",
+                    "updatedAt": "2023-04-01T00:00:00.000Z",
+                },
+            ],
+        })))
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/v1/invite-org/signup"))
+        .and(body_json(json!({
+            "inviteeEmail": "john@example.com",
+            "organizationId": "organization-id",
+        })))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "message": "Successfully invited user to the organization",
+            "completeInviteLink": "https://app.infisical.com/signupinvite?token=token",
+        })))
+        .expect(2)
+        .mount(&server)
+        .await;
+    Mock::given(method("DELETE"))
+        .and(path(
+            "/v2/organizations/organization-id/memberships/invitation-id",
+        ))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "membership": organization_membership("member"),
+        })))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let client = client(&server).await;
+    let link = client
+        .invite_organization_member("organization-id", "john@example.com")
+        .await
+        .unwrap();
+    assert_eq!(
+        link.as_deref(),
+        Some("https://app.infisical.com/signupinvite?token=token")
+    );
+
+    let pending = client
+        .get_pending_organization_invitations("organization-id")
+        .await
+        .unwrap();
+    assert_eq!(pending.len(), 1);
+    assert_eq!(pending[0].email(), Some("john@example.com"));
+    assert!(pending[0].user.is_none());
+
+    client
+        .resend_organization_invitation("organization-id", "john@example.com")
+        .await
+        .unwrap();
+    let err = client
+        .resend_organization_invitation("organization-id", "jane@example.com")
+        .await
+        .unwrap_err();
+    assert!(err.is_not_found());
+
+    client
+        .revoke_organization_invitation("organization-id", "invitation-id")
+        .await
+        .unwrap();
+    let err = client
+        .revoke_organization_invitation("organization-id", "membership-id")
+        .await
+        .unwrap_err();
+    assert!(err.is_not_found());
+}
+
+#[tokio::test]
+async fn set_denied_permissions_sends_typed_permissions() {
+    let server = MockServer::start().await;
+    let permissions = json!([
+        { "ability": "write", "environmentSlug": "staging" },
+        { "ability": "read", "environmentSlug": "prod" },
+        { "ability": "write", "environmentSlug": "prod" },
+    ]);
+    Mock::given(method("POST"))
+        .and(path("/v1/membership/membership-id/deny-permissions"))
+        .and(body_json(json!({ "permissions": permissions })))
+        .respond_with(
+            ResponseTemplate::new(200).set_body_json(json!({ "permissionsDenied": permissions })),
+        )
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let denied = client(&server)
+        .await
+        .set_denied_permissions(
+            "membership-id",
+            vec![
+                DeniedPermission::write("staging"),
+                DeniedPermission::read("prod"),
+                DeniedPermission::write("prod"),
+            ],
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(denied.len(), 3);
+    assert_eq!(denied[0].ability, Ability::Write);
+}
+
+#[tokio::test]
+async fn manage_custom_roles() {
+    let server = MockServer::start().await;
+    let role = json!({
+        "_id": "role-id",
+        "name": "Deployer",
+        "slug": "deployer",
+        "permissions": [
+            { "action": "read", "subject": "secrets" },
+            { "action": "edit", "subject": "secrets", "conditions": { "environment": "staging" } },
+        ],
+        "workspace": "workspace-id",
+    });
+    Mock::given(method("GET"))
+        .and(path("/v1/roles"))
+        .and(query_param("orgId", "organization-id"))
+        .and(query_param("workspaceId", "workspace-id"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "data": {
+                "roles": [
+                    role,
+                    {
+                        "name": "Viewer",
+                        "slug": "viewer",
+                        "permissions": [{ "action": "read", "subject": "secrets" }],
+                    },
+                ],
+            },
+        })))
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/v1/roles"))
+        .and(body_json(json!({
+            "orgId": "organization-id",
+            "workspaceId": "workspace-id",
+            "name": "Deployer",
+            "slug": "deployer",
+            "permissions": role["permissions"],
+        })))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "role": role })))
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("PATCH"))
+        .and(path("/v1/roles/role-id"))
+        .and(body_json(json!({
+            "orgId": "organization-id",
+            "workspaceId": "workspace-id",
+            "name": "Deployer",
+            "slug": "deployer",
+            "description": "Deploys to staging",
+            "permissions": role["permissions"],
+        })))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "role": role })))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let mut definition = RoleDefinition {
+        name: "Deployer".to_string(),
+        slug: "deployer".to_string(),
+        description: None,
+        permissions: vec![
+            RolePermission::allow(PermissionAction::Read, rbac::SECRETS_SUBJECT),
+            RolePermission::allow(PermissionAction::Edit, rbac::SECRETS_SUBJECT)
+                .in_environment("staging"),
+        ],
+    };
+    let client = client(&server).await;
+    let created = client
+        .create_role("organization-id", Some("workspace-id"), definition.clone())
+        .await
+        .unwrap();
+    definition.description = Some("Deploys to staging".to_string());
+    client
+        .update_role(
+            "role-id",
+            "organization-id",
+            Some("workspace-id"),
+            definition,
+        )
+        .await
+        .unwrap();
+    let roles = client
+        .get_roles("organization-id", Some("workspace-id"))
+        .await
+        .unwrap();
+
+    assert_eq!(created.id.as_deref(), Some("role-id"));
+    assert_eq!(roles.len(), 2);
+    assert_eq!(roles[1].id, None);
+}
+
+fn project_log(id: &str) -> serde_json::Value {
+    json!({
+        "_id": id,
+        "user": {
+            "_id": "user-id",
+            "email": "jane@example.com",
+            "firstName": "Jane",
+            "lastName": "Doe",
+            "__v": 0,
+            "devices": [],
+            "isMfaEnabled": false,
+            "mfaMethods": [],
+            "createdAt": "2023-04-01T00:00:00.000Z",
+            "updatedAt": "2023-04-01T00:00:00.000Z",
+        },
+        "workspace": "workspace-id",
+        "actionNames": ["readSecrets"],
+        "actions": [],
+        "channel": "cli",
+        "ipAddress": "127.0.0.1",
+        "createdAt": "2023-04-01T00:00:00.000Z",
+        "updatedAt": "2023-04-01T00:00:00.000Z",
+    })
+}
+
+#[tokio::test]
+async fn streams_page_until_exhausted() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/v1/workspace/workspace-id/logs"))
+        .and(query_param("offset", "0"))
+        .and(query_param("limit", "2"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "logs": [project_log("log-1"), project_log("log-2")],
+        })))
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/v1/workspace/workspace-id/logs"))
+        .and(query_param("offset", "2"))
+        .and(query_param("limit", "2"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "logs": [project_log("log-3")],
+        })))
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/v1/workspace/workspace-id/secret-snapshots"))
+        .and(query_param("offset", "0"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "secretSnapshots": [] })))
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/v1/secret/secret-id/secret-versions"))
+        .respond_with(ResponseTemplate::new(500).set_body_json(error_body(500, "Failed")))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let client = client(&server).await;
+    let page_size = PageSize::new(2).unwrap();
+    let logs: Vec<_> = client
+        .logs_stream("workspace-id", LogQuery::new(), page_size)
+        .try_collect()
+        .await
+        .unwrap();
+    let snapshots: Vec<_> = client
+        .snapshots_stream("workspace-id", page_size)
+        .try_collect()
+        .await
+        .unwrap();
+    let versions: Vec<_> = client
+        .secret_versions_stream("secret-id", page_size)
+        .collect()
+        .await;
+
+    let ids: Vec<_> = logs.iter().map(|log| log.id.as_str()).collect();
+    assert_eq!(ids, ["log-1", "log-2", "log-3"]);
+    assert!(snapshots.is_empty());
+    assert_eq!(versions.len(), 1);
+    assert!(versions[0].as_ref().err().unwrap().is_api());
+}
+
+#[tokio::test]
+async fn get_project_logs_sends_query_parameters() {
+    let server = MockServer::start().await;
+    // Infisical is expected to apply the filters, but logs that don't match are dropped anyway
+    let mut matching = project_log("log-1");
+    matching["actionNames"] = json!(["updateSecrets"]);
+    let mut too_late = project_log("too-late");
+    too_late["actionNames"] = json!(["updateSecrets"]);
+    too_late["createdAt"] = json!("2023-06-01T00:00:00.000Z");
+    let other_action = project_log("other-action");
+    Mock::given(method("GET"))
+        .and(path("/v1/workspace/workspace-id/logs"))
+        .and(query_param("userId", "user-id"))
+        .and(query_param("actionNames", "updateSecrets,deleteSecrets"))
+        .and(query_param("sortBy", "oldest"))
+        .and(query_param("startDate", "2023-04-01T00:00:00Z"))
+        .and(query_param("endDate", "2023-05-01T00:00:00Z"))
+        .and(query_param("offset", "0"))
+        .and(query_param("limit", "10"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "logs": [matching, too_late, other_action],
+        })))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let april_1st = OffsetDateTime::from_unix_timestamp(1_680_307_200).unwrap();
+    let may_1st = OffsetDateTime::from_unix_timestamp(1_682_899_200).unwrap();
+    let query = LogQuery::new()
+        .user("user-id")
+        .action(LogAction::UpdateSecrets)
+        .action(LogAction::DeleteSecrets)
+        .since(april_1st)
+        .until(may_1st)
+        .sort(SortOrder::Oldest)
+        .limit(10);
+    let client = client(&server).await;
+    let logs = client
+        .get_project_logs("workspace-id", &query)
+        .await
+        .unwrap();
+
+    let ids: Vec<_> = logs.iter().map(|log| log.id.as_str()).collect();
+    assert_eq!(ids, ["log-1"]);
+    assert_eq!(logs[0].action_names, [LogAction::UpdateSecrets]);
+
+    let reversed = LogQuery::new().since(may_1st).until(april_1st);
+    let err = client
+        .get_project_logs("workspace-id", &reversed)
+        .await
+        .err()
+        .unwrap();
+    assert!(err.is_invalid_input());
+}
+
+fn secret_version(id: &str, key: &str, value: &str, project_key: &ProjectKey) -> serde_json::Value {
+    let key = project_key.encrypt(key).unwrap();
+    let value = project_key.encrypt(value).unwrap();
+
+    json!({
+        "_id": id,
+        "tags": [],
+        "secret": "secret-id",
+        "version": 1,
+        "workspace": "workspace-id",
+        "type": "shared",
+        "environment": "dev",
+        "isDeleted": false,
+        "secretKeyCiphertext": key.text,
+        "secretKeyIV": key.nonce,
+        "secretKeyTag": key.tag,
+        "secretValueCiphertext": value.text,
+        "secretValueIV": value.nonce,
+        "secretValueTag": value.tag,
+        "__v": 0,
+        "createdAt": "2023-04-01T00:00:00.000Z",
+        "updatedAt": "2023-04-01T00:00:00.000Z",
+    })
+}
+
+#[tokio::test]
+async fn get_change_events_resolves_and_decrypts_versions() {
+    let server = MockServer::start().await;
+    let project_key = ProjectKey::generate();
+    let mut log = project_log("log-1");
+    log["actionNames"] = json!(["updateSecrets"]);
+    log["actions"] = json!([{
+        "name": "updateSecrets",
+        "user": "user-id",
+        "workspace": "workspace-id",
+        "payload": [
+            {
+                "secret": "secret-id",
+                "oldSecretVersion": "version-1",
+                "newSecretVersion": "version-2",
+            },
+            { "secret": "secret-id", "newSecretVersion": "corrupted-version" },
+            { "secret": "deleted-secret-id", "oldSecretVersion": "deleted-version" },
+        ],
+    }]);
+    Mock::given(method("GET"))
+        .and(path("/v1/workspace/workspace-id/logs"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "logs": [log] })))
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/v2/secrets"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "secrets": [] })))
+        .expect(0)
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/v1/secret/secret-id/secret-versions"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "secretVersions": [
+                secret_version(
+                    "corrupted-version",
+                    "API_KEY",
+                    "newer",
+                    &ProjectKey::generate()
+                ),
+                secret_version("version-2", "API_KEY", "new", &project_key),
+                secret_version("version-1", "API_KEY", "old", &project_key),
+            ],
+        })))
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/v1/secret/deleted-secret-id/secret-versions"))
+        .respond_with(
+            ResponseTemplate::new(404).set_body_json(json!({ "message": "Secret not found" })),
+        )
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let events = client(&server)
+        .await
+        .get_change_events(
+            "workspace-id",
+            &LogQuery::new(),
+            &project_key,
+            &Redaction::None,
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(events.events.len(), 1);
+    assert_eq!(
+        events.events[0].to_string(),
+        "API_KEY changed by jane@example.com at 2023-04-01T00:00:00Z from env dev"
+    );
+    assert!(matches!(
+        &events.events[0].old_value,
+        Some(ChangeValue::Plain(value)) if value.expose() == "old"
+    ));
+    assert_eq!(events.unresolved, ["deleted-version"]);
+    assert_eq!(events.failures.len(), 1);
+    assert_eq!(events.failures[0].id, "corrupted-version");
+}